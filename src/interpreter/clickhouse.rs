@@ -1,13 +1,22 @@
-use crate::interpreter::{options::ClickHouseOptions, ClickHouseAvailableQuirks, ClickHouseQuirks};
-use anyhow::{Error, Result};
+use crate::interpreter::{
+    options::{ClickHouseOptions, FILTER_COLUMNS},
+    ssh_tunnel::SshTunnel,
+    ClickHouseAvailableQuirks, ClickHouseQuirks,
+};
+use anyhow::{anyhow, Error, Result};
 use chrono::{DateTime, Local};
 use clickhouse_rs::{
+    errors::Error as DriverCrateError,
     types::{Complex, FromSql},
     Block, Options, Pool,
 };
 use futures_util::StreamExt;
 use std::collections::HashMap;
+use std::future::Future;
+use std::io;
 use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
 
 // TODO:
 // - implement parsing using serde
@@ -16,11 +25,135 @@ use std::str::FromStr;
 
 pub type Columns = Block<Complex>;
 
+// Filter for the queries views: LIKE over the filter columns (see --filter-columns, by default
+// client/user/query_id/query), or regular expression for the query if the filter starts with ~
+// (i.e. ~INSERT.*\.tmp)
+fn get_queries_filter(filter: &str, columns: &Option<Vec<String>>) -> String {
+    if let Some(regexp) = filter.strip_prefix('~') {
+        return format!("match(query, '{}')", escape_string(regexp));
+    }
+    let columns = columns
+        .as_ref()
+        .map(|c| c.iter().map(String::as_str).collect::<Vec<&str>>())
+        .unwrap_or(FILTER_COLUMNS.to_vec());
+    let conditions = columns
+        .iter()
+        .map(|column| {
+            if *column == "initial_address" {
+                return format!("toString({}) LIKE '{}'", column, escape_string(filter));
+            }
+            return format!("{} LIKE '{}'", column, escape_string(filter));
+        })
+        .collect::<Vec<String>>();
+    return format!("({})", conditions.join(" OR "));
+}
+
+// How many times the query is retried on connection loss (i.e. server restart)
+const RECONNECT_RETRIES: u32 = 3;
+// Backoff before the first retry, doubled for each next one
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+
+type ReconnectCallback = Box<dyn Fn(String) + Send + Sync>;
+
+// Escape value for the string literal (i.e. '{}')
+pub fn escape_string(value: &str) -> String {
+    return value.replace('\\', "\\\\").replace('\'', "\\'");
+}
+
+// Escape values for the list of string literals (i.e. IN ('{}'))
+fn escape_strings(values: &[String]) -> String {
+    return values
+        .iter()
+        .map(|value| escape_string(value))
+        .collect::<Vec<String>>()
+        .join("','");
+}
+
+// database.table with backticks (for identifiers that require quoting)
+fn quote_table(database: &str, table: &str) -> String {
+    let quote = |name: &str| format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"));
+    return format!("{}.{}", quote(database), quote(table));
+}
+
 pub struct ClickHouse {
     options: ClickHouseOptions,
     quirks: ClickHouseQuirks,
 
     pool: Pool,
+    // Notified (with the status message) before each reconnect attempt
+    on_reconnect: Mutex<Option<ReconnectCallback>>,
+    // --ssh-jump (the pool is connected to the local end of the tunnel)
+    ssh_tunnel: Option<SshTunnel>,
+}
+
+fn is_connection_io_error(err: &io::Error) -> bool {
+    return matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+    );
+}
+
+/// Is it connection loss (i.e. server had been restarted), after which the query can be retried,
+/// unlike errors from the server (i.e. syntax error) or timeouts (i.e. --query-timeout)
+pub fn is_connection_error(err: &Error) -> bool {
+    if let Some(DriverCrateError::Io(err)) = err.downcast_ref::<DriverCrateError>() {
+        return is_connection_io_error(err);
+    }
+    return err
+        .chain()
+        .filter_map(|err| err.downcast_ref::<io::Error>())
+        .any(is_connection_io_error);
+}
+
+// Only queries that does not modify anything can be retried, since on connection loss it is
+// unknown whether the query had been executed or not (i.e. KILL, OPTIMIZE, ATTACH PART)
+fn is_read_only_query(query: &str) -> bool {
+    let first_word = query
+        .trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    return matches!(
+        first_word.as_str(),
+        "SELECT" | "WITH" | "EXPLAIN" | "SHOW" | "DESCRIBE" | "DESC" | "EXISTS" | "USE"
+    );
+}
+
+// Retries the query on connection loss (for read-only queries only) with exponential backoff,
+// on_retry is called before each retry (error from it stops retrying)
+async fn retry_on_connection_loss<T, F, Fut, R>(
+    query: &str,
+    backoff: Duration,
+    mut on_retry: R,
+    f: F,
+) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T>>,
+    R: FnMut(u32, &Error) -> Result<()>,
+{
+    let mut attempt = 0;
+    loop {
+        let result = f().await;
+        match result {
+            Err(err)
+                if attempt < RECONNECT_RETRIES
+                    && is_connection_error(&err)
+                    && is_read_only_query(query) =>
+            {
+                attempt += 1;
+                on_retry(attempt, &err)?;
+                tokio::time::sleep(backoff * 2_u32.pow(attempt - 1)).await;
+            }
+            _ => return result,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -31,6 +164,23 @@ pub enum TraceType {
     Memory,
 }
 
+// Filter for the system.text_log
+#[derive(Debug, Default, Clone)]
+pub struct TextLogFilter {
+    // Show only messages with this level or more important (i.e. Information hides Debug/Trace)
+    pub max_level: Option<&'static str>,
+    // Substring of the message
+    pub message: String,
+}
+
+// Commands for the table from the views with database/table columns
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TableCommand {
+    StopMerges,
+    StartMerges,
+    OptimizeFinal,
+}
+
 #[derive(Default)]
 pub struct ClickHouseServerCPU {
     pub count: u64,
@@ -53,6 +203,25 @@ pub struct ClickHouseServerThreadPools {
     pub remote_io: u64,
     pub queries: u64,
 }
+impl ClickHouseServerThreadPools {
+    /// Pools with their (short) names, in the order of the summary
+    pub fn named(&self) -> Vec<(&'static str, u64)> {
+        return vec![
+            ("Merges", self.merges_mutations),
+            ("Fetches", self.fetches),
+            ("Common", self.common),
+            ("Moves", self.moves),
+            ("Schedule", self.schedule),
+            ("Buffer", self.buffer_flush),
+            ("Distributed", self.distributed),
+            ("Brokers", self.message_broker),
+            ("Backups", self.backups),
+            ("IO", self.io),
+            ("RemoteIO", self.remote_io),
+            ("Queries", self.queries),
+        ];
+    }
+}
 #[derive(Default)]
 pub struct ClickHouseServerThreads {
     pub os_total: u64,
@@ -91,6 +260,8 @@ pub struct ClickHouseServerUptime {
 pub struct ClickHouseServerBlockDevices {
     pub read_bytes: u64,
     pub write_bytes: u64,
+    // Per device (device, read_bytes, write_bytes), device is prefixed with host in cluster mode
+    pub devices: Vec<(String, u64, u64)>,
 }
 #[derive(Default)]
 pub struct ClickHouseServerStorages {
@@ -124,6 +295,49 @@ pub struct ClickHouseServerSummary {
     pub blkdev: ClickHouseServerBlockDevices,
     pub update_interval: u64,
 }
+impl ClickHouseServerSummary {
+    /// Metrics that can be compared between summaries (see summary baselines), the ones with
+    /// "_bytes" suffix are sizes
+    pub fn metrics(&self) -> Vec<(&'static str, u64)> {
+        return vec![
+            ("memory_resident_bytes", self.memory.resident),
+            ("memory_tracked_bytes", self.memory.tracked),
+            ("memory_tables_bytes", self.memory.tables),
+            ("memory_caches_bytes", self.memory.caches),
+            ("memory_queries_bytes", self.memory.processes),
+            ("memory_merges_bytes", self.memory.merges),
+            ("cpu_used", self.cpu.user + self.cpu.system),
+            ("threads", self.threads.os_total),
+            ("queries", self.processes),
+            ("merges", self.merges),
+            ("mutations", self.mutations),
+            ("replication_queue", self.replication_queue),
+            ("fetches", self.fetches),
+            ("selected_rows", self.rows.selected),
+            ("inserted_rows", self.rows.inserted),
+            ("buffer_bytes", self.storages.buffer_bytes),
+            (
+                "distributed_insert_files",
+                self.storages.distributed_insert_files,
+            ),
+        ];
+    }
+}
+
+// Index analysis estimation (from EXPLAIN PLAN indexes=1) for one MergeTree source
+#[derive(Default, Debug)]
+pub struct IndexesEstimation {
+    pub source: String,
+    // (selected, total) after the last index
+    pub parts: Option<(u64, u64)>,
+    pub granules: Option<(u64, u64)>,
+}
+
+// Parse "x/y"
+fn parse_ratio(value: &str) -> Option<(u64, u64)> {
+    let (selected, total) = value.trim().split_once('/')?;
+    return Some((selected.parse().ok()?, total.parse().ok()?));
+}
 
 fn collect_values<'b, T: FromSql<'b>>(block: &'b Columns, column: &str) -> Vec<T> {
     return (0..block.row_count())
@@ -133,8 +347,31 @@ fn collect_values<'b, T: FromSql<'b>>(block: &'b Columns, column: &str) -> Vec<T
 
 impl ClickHouse {
     pub async fn new(options: ClickHouseOptions) -> Result<Self> {
-        let url = options.url.clone().unwrap();
-        let connect_options: Options = Options::from_str(&url)?
+        let mut url = options.url.clone().unwrap();
+        let mut ssh_tunnel = None;
+        if let Some(jump) = &options.ssh_jump {
+            let mut tunnel_url = url::Url::parse(&url)?;
+            let tunnel = SshTunnel::new(
+                jump,
+                tunnel_url.host_str().unwrap(),
+                tunnel_url.port().unwrap(),
+            )
+            .await?;
+            tunnel_url.set_host(Some("127.0.0.1"))?;
+            tunnel_url
+                .set_port(Some(tunnel.local_port()))
+                .map_err(|_| anyhow!("Cannot set port for {}", url))?;
+            // Other hosts (--host) are not reachable without the tunnel
+            let pairs = tunnel_url
+                .query_pairs()
+                .into_owned()
+                .filter(|(key, _)| key != "alt_hosts")
+                .collect::<Vec<_>>();
+            tunnel_url.query_pairs_mut().clear().extend_pairs(pairs);
+            url = tunnel_url.to_string();
+            ssh_tunnel = Some(tunnel);
+        }
+        let mut connect_options: Options = Options::from_str(&url)?
             .with_setting(
                 "storage_system_stack_trace_pipe_read_timeout_ms",
                 1000,
@@ -146,17 +383,31 @@ impl ClickHouse {
             //
             .with_setting("allow_experimental_analyzer", false, true)
             // TODO: add support of Map type for LowCardinality in the driver
-            .with_setting("low_cardinality_allow_in_native_format", false, true);
+            .with_setting("low_cardinality_allow_in_native_format", false, true)
+            // pool_min should not exceed pool_max
+            .pool_min(options.max_connections.min(2))
+            .pool_max(options.max_connections);
+        if options.profile {
+            log::warn!("Profiling is enabled for all queries (--profile), it adds some overhead");
+            connect_options = connect_options
+                .with_setting("query_profiler_real_time_period_ns", 10_000_000, false)
+                .with_setting("query_profiler_cpu_time_period_ns", 10_000_000, false)
+                .with_setting("memory_profiler_step", 1_048_576, false)
+                .with_setting("log_processors_profiles", true, false);
+        }
         let pool = Pool::new(connect_options);
 
         let version = pool
             .get_handle()
             .await
             .map_err(|e| {
-                Error::msg(format!(
+                if let Some(Err(err)) = ssh_tunnel.as_ref().map(SshTunnel::check) {
+                    return err;
+                }
+                return Error::msg(format!(
                     "Cannot connect to ClickHouse at {} ({})",
                     options.url_safe, e
-                ))
+                ));
             })?
             .query("SELECT version()")
             .fetch_all()
@@ -167,9 +418,15 @@ impl ClickHouse {
             options,
             quirks,
             pool,
+            on_reconnect: Mutex::new(None),
+            ssh_tunnel,
         });
     }
 
+    pub fn set_on_reconnect<F: Fn(String) + Send + Sync + 'static>(&self, callback: F) {
+        *self.on_reconnect.lock().unwrap() = Some(Box::new(callback));
+    }
+
     pub fn version(&self) -> String {
         return self.quirks.get_version();
     }
@@ -215,7 +472,14 @@ impl ClickHouse {
                         // Compatility with system.processlist
                         memory_usage::Int64 AS peak_memory_usage,
                         query_duration_ms/1e3 AS elapsed,
+                        read_rows,
+                        read_bytes,
+                        0::UInt64 AS total_rows_approx,
+                        result_rows,
+                        result_bytes,
                         user,
+                        /* the original client (for subqueries too), without IPv4-mapped prefix */
+                        replaceRegexpOne(toString(initial_address), '^::ffff:', '') AS client_address,
                         is_initial_query,
                         initial_query_id,
                         query_id,
@@ -223,6 +487,8 @@ impl ClickHouse {
                         current_database,
                         query_start_time_microseconds,
                         event_time_microseconds AS query_end_time_microseconds,
+                        exception_code = 394 /* QUERY_WAS_CANCELLED */ AS is_killed,
+                        if(is_killed, exception, '') AS kill_reason,
                         toValidUTF8(query) AS original_query,
                         normalizeQuery(query) AS normalized_query
                     FROM {db_table}
@@ -234,7 +500,7 @@ impl ClickHouse {
                 "#,
                     db_table = dbtable,
                     filter = if !filter.is_empty() {
-                        format!("AND (client_hostname LIKE '{0}' OR os_user LIKE '{0}' OR user LIKE '{0}' OR initial_user LIKE '{0}' OR client_name LIKE '{0}' OR query_id LIKE '{0}' OR query LIKE '{0}')", &filter)
+                        format!("AND {}", get_queries_filter(filter, &self.options.filter_columns))
                     } else {
                         "".to_string()
                     }
@@ -286,7 +552,14 @@ impl ClickHouse {
                         // Compatility with system.processlist
                         memory_usage::Int64 AS peak_memory_usage,
                         query_duration_ms/1e3 AS elapsed,
+                        read_rows,
+                        read_bytes,
+                        0::UInt64 AS total_rows_approx,
+                        result_rows,
+                        result_bytes,
                         user,
+                        /* the original client (for subqueries too), without IPv4-mapped prefix */
+                        replaceRegexpOne(toString(initial_address), '^::ffff:', '') AS client_address,
                         is_initial_query,
                         initial_query_id,
                         query_id,
@@ -294,6 +567,8 @@ impl ClickHouse {
                         current_database,
                         query_start_time_microseconds,
                         event_time_microseconds AS query_end_time_microseconds,
+                        exception_code = 394 /* QUERY_WAS_CANCELLED */ AS is_killed,
+                        if(is_killed, exception, '') AS kill_reason,
                         toValidUTF8(query) AS original_query,
                         normalizeQuery(query) AS normalized_query
                     FROM {db_table}
@@ -305,7 +580,7 @@ impl ClickHouse {
                 "#,
                     db_table = dbtable,
                     filter = if !filter.is_empty() {
-                        format!("AND (client_hostname LIKE '{0}' OR os_user LIKE '{0}' OR user LIKE '{0}' OR initial_user LIKE '{0}' OR client_name LIKE '{0}' OR query_id LIKE '{0}' OR query LIKE '{0}')", &filter)
+                        format!("AND {}", get_queries_filter(filter, &self.options.filter_columns))
                     } else {
                         "".to_string()
                     }
@@ -329,7 +604,12 @@ impl ClickHouse {
                         thread_ids,
                         peak_memory_usage,
                         elapsed / {q} AS elapsed,
+                        read_rows,
+                        read_bytes,
+                        total_rows_approx,
                         user,
+                        /* the original client (for subqueries too), without IPv4-mapped prefix */
+                        replaceRegexpOne(toString(initial_address), '^::ffff:', '') AS client_address,
                         is_initial_query,
                         initial_query_id,
                         query_id,
@@ -360,7 +640,7 @@ impl ClickHouse {
                         "current_database"
                     },
                     filter = if !filter.is_empty() {
-                        format!("WHERE (client_hostname LIKE '{0}' OR os_user LIKE '{0}' OR user LIKE '{0}' OR initial_user LIKE '{0}' OR client_name LIKE '{0}' OR query_id LIKE '{0}' OR query LIKE '{0}')", &filter)
+                        format!("WHERE {}", get_queries_filter(&filter, &self.options.filter_columns))
                     } else {
                         "".to_string()
                     }
@@ -413,7 +693,7 @@ impl ClickHouse {
                     (
                         WITH
                             -- exclude MD/LVM
-                            metric LIKE '%_sd%' OR metric LIKE '%_nvme%' OR metric LIKE '%_vd%' AS is_disk,
+                            metric LIKE '%\\_sd%' OR metric LIKE '%\\_nvme%' OR metric LIKE '%\\_vd%' AS is_disk,
                             metric LIKE '%vlan%' AS is_vlan
                         -- NOTE: cast should be after aggregation function since the type is Float64
                         SELECT
@@ -506,6 +786,36 @@ impl ClickHouse {
             )
             .await?;
 
+        let devices_block = self
+            .execute(&format!(
+                r#"
+                WITH metric LIKE '%\\_sd%' OR metric LIKE '%\\_nvme%' OR metric LIKE '%\\_vd%' AS is_disk
+                SELECT
+                    {host} replaceRegexpOne(metric, '^Block(Read|Write)Bytes_', '') AS device,
+                    CAST(sumIf(value, metric LIKE 'BlockReadBytes%') AS UInt64) AS read_bytes,
+                    CAST(sumIf(value, metric LIKE 'BlockWriteBytes%') AS UInt64) AS write_bytes
+                FROM {asynchronous_metrics}
+                WHERE (metric LIKE 'BlockReadBytes%' OR metric LIKE 'BlockWriteBytes%') AND is_disk
+                GROUP BY device
+                ORDER BY device
+                "#,
+                host = if self.options.cluster.is_some() {
+                    "hostName() || ':' ||"
+                } else {
+                    ""
+                },
+                asynchronous_metrics = self.get_table_name("system.asynchronous_metrics"),
+            ))
+            .await?;
+        let mut devices = Vec::new();
+        for i in 0..devices_block.row_count() {
+            devices.push((
+                devices_block.get::<String, _>(i, "device")?,
+                devices_block.get::<u64, _>(i, "read_bytes")?,
+                devices_block.get::<u64, _>(i, "write_bytes")?,
+            ));
+        }
+
         let get = |key: &str| {
             // By subquery.column
             if let Ok(value) = block.get::<u64, _>(0, key) {
@@ -591,25 +901,102 @@ impl ClickHouse {
             blkdev: ClickHouseServerBlockDevices {
                 read_bytes: get("asynchronous_metrics.block_read_bytes"),
                 write_bytes: get("asynchronous_metrics.block_write_bytes"),
+                devices,
             },
 
             update_interval: get("asynchronous_metrics.metrics_update_interval"),
         });
     }
 
-    pub async fn kill_query(&self, query_id: &str) -> Result<()> {
-        let &query;
+    pub fn get_kill_query(&self, query_id: &str) -> String {
         if let Some(cluster) = self.options.cluster.as_ref() {
-            query = format!(
+            return format!(
                 "KILL QUERY ON CLUSTER {} WHERE query_id = '{}' SYNC",
                 cluster, query_id
             );
         } else {
-            query = format!("KILL QUERY WHERE query_id = '{}' SYNC", query_id);
+            return format!("KILL QUERY WHERE query_id = '{}' SYNC", query_id);
         }
+    }
+
+    pub async fn kill_query(&self, query_id: &str) -> Result<()> {
+        return self.execute_simple(&self.get_kill_query(query_id)).await;
+    }
+
+    pub async fn execute_table_command(
+        &self,
+        command: TableCommand,
+        database: &str,
+        table: &str,
+    ) -> Result<()> {
+        let on_cluster = self
+            .options
+            .cluster
+            .as_ref()
+            .map(|cluster| format!(" ON CLUSTER {}", cluster))
+            .unwrap_or_default();
+        let table = quote_table(database, table);
+        let query = match command {
+            TableCommand::StopMerges => {
+                format!("SYSTEM STOP MERGES{} {}", on_cluster, table)
+            }
+            TableCommand::StartMerges => {
+                format!("SYSTEM START MERGES{} {}", on_cluster, table)
+            }
+            TableCommand::OptimizeFinal => {
+                format!("OPTIMIZE TABLE {}{} FINAL", table, on_cluster)
+            }
+        };
+        return self.execute_simple(&query).await;
+    }
+
+    /// SYSTEM RELOAD DICTIONARY (or all dictionaries if name is not specified)
+    pub async fn reload_dictionary(&self, name: Option<&str>) -> Result<()> {
+        let on_cluster = self
+            .options
+            .cluster
+            .as_ref()
+            .map(|cluster| format!(" ON CLUSTER {}", cluster))
+            .unwrap_or_default();
+        let query = match name {
+            Some(name) => format!("SYSTEM RELOAD DICTIONARY{} {}", on_cluster, name),
+            None => format!("SYSTEM RELOAD DICTIONARIES{}", on_cluster),
+        };
+        return self.execute_simple(&query).await;
+    }
+
+    /// ATTACH PART (attach = true) or DROP DETACHED PART for the part from detached/
+    pub async fn alter_detached_part(
+        &self,
+        database: &str,
+        table: &str,
+        part: &str,
+        attach: bool,
+    ) -> Result<()> {
+        let on_cluster = self
+            .options
+            .cluster
+            .as_ref()
+            .map(|cluster| format!(" ON CLUSTER {}", cluster))
+            .unwrap_or_default();
+        let table = quote_table(database, table);
+        let part = escape_string(part);
+        let query = if attach {
+            format!("ALTER TABLE {}{} ATTACH PART '{}'", table, on_cluster, part)
+        } else {
+            format!(
+                "ALTER TABLE {}{} DROP DETACHED PART '{}' SETTINGS allow_drop_detached=1",
+                table, on_cluster, part
+            )
+        };
         return self.execute_simple(&query).await;
     }
 
+    /// Lightweight query to keep the connection alive
+    pub async fn ping(&self) -> Result<()> {
+        return self.execute_simple("SELECT 1").await;
+    }
+
     pub async fn execute_query(&self, database: &str, query: &str) -> Result<()> {
         self.execute_simple(&format!("USE {}", database)).await?;
         return self.execute_simple(query).await;
@@ -630,6 +1017,11 @@ impl ClickHouse {
         return self.explain("PLAN actions=1", database, query, None).await;
     }
 
+    // Plan without actions (only steps), i.e. for rendering it as a graph
+    pub async fn explain_plan_steps(&self, database: &str, query: &str) -> Result<Vec<String>> {
+        return self.explain("PLAN", database, query, None).await;
+    }
+
     pub async fn explain_pipeline(&self, database: &str, query: &str) -> Result<Vec<String>> {
         return self.explain("PIPELINE", database, query, None).await;
     }
@@ -645,6 +1037,39 @@ impl ClickHouse {
         return self.explain("PLAN indexes=1", database, query, None).await;
     }
 
+    pub async fn get_indexes_estimation(
+        &self,
+        database: &str,
+        query: &str,
+    ) -> Result<Vec<IndexesEstimation>> {
+        let plan = self.explain_plan_indexes(database, query).await?;
+
+        let mut result = Vec::<IndexesEstimation>::new();
+        for line in plan {
+            let line = line.trim();
+            // ReadFromMergeTree (db.table)
+            if let Some(source) = line.strip_prefix("ReadFromMergeTree") {
+                result.push(IndexesEstimation {
+                    source: source
+                        .trim()
+                        .trim_matches(|c| c == '(' || c == ')')
+                        .to_string(),
+                    ..Default::default()
+                });
+                continue;
+            }
+            // Every index overrides the previous values, so only the last one is left
+            if let Some(estimation) = result.last_mut() {
+                if let Some(parts) = line.strip_prefix("Parts:") {
+                    estimation.parts = parse_ratio(parts);
+                } else if let Some(granules) = line.strip_prefix("Granules:") {
+                    estimation.granules = parse_ratio(granules);
+                }
+            }
+        }
+        return Ok(result);
+    }
+
     // TODO: copy all settings from the query
     async fn explain(
         &self,
@@ -698,6 +1123,7 @@ impl ClickHouse {
         query_ids: &Option<Vec<String>>,
         start_microseconds: DateTime<Local>,
         end_microseconds: Option<DateTime<Local>>,
+        filter: &TextLogFilter,
     ) -> Result<Columns> {
         // TODO:
         // - optional flush, but right now it gives "blocks should not be empty." error
@@ -730,6 +1156,8 @@ impl ClickHouse {
                             event_date >= toDate(start_time_) AND event_time >= toDateTime(start_time_) AND event_time_microseconds > start_time_
                         AND event_date <= toDate(end_time_)   AND event_time <= toDateTime(end_time_)   AND event_time_microseconds <= end_time_
                         {}
+                        {}
+                        {}
                         // TODO: if query finished, add filter for event_time end range
                     ORDER BY event_date, event_time, event_time_microseconds
                     "#,
@@ -742,7 +1170,20 @@ impl ClickHouse {
                         .ok_or(Error::msg("Invalid end time"))?,
                     dbtable,
                     if let Some(query_ids) = query_ids {
-                        format!("AND query_id IN ('{}')", query_ids.join("','"))
+                        format!("AND query_id IN ('{}')", escape_strings(query_ids))
+                    } else {
+                        "".into()
+                    },
+                    if let Some(max_level) = filter.max_level {
+                        format!("AND level <= '{}'", max_level)
+                    } else {
+                        "".into()
+                    },
+                    if !filter.message.is_empty() {
+                        format!(
+                            "AND position(message, '{}') > 0",
+                            escape_string(&filter.message)
+                        )
                     } else {
                         "".into()
                     }
@@ -806,7 +1247,7 @@ impl ClickHouse {
                 dbtable,
                 trace_type,
                 if query_ids.is_some() {
-                    format!("AND query_id IN ('{}')", query_ids.unwrap().join("','"))
+                    format!("AND query_id IN ('{}')", escape_strings(query_ids.unwrap()))
                 } else {
                     "".to_string()
                 },
@@ -814,6 +1255,257 @@ impl ClickHouse {
             .await;
     }
 
+    /// Raw (not symbolized and not aggregated) trace_log rows of the queries, all columns are
+    /// converted to String for export.
+    pub async fn get_query_raw_trace_log(
+        &self,
+        query_ids: &[String],
+        start_microseconds: DateTime<Local>,
+        end_microseconds: Option<DateTime<Local>>,
+    ) -> Result<Columns> {
+        let dbtable = self.get_table_name("system.trace_log");
+        return self
+            .execute(&format!(
+                r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_time_,
+                {end} AS end_time_
+            SELECT
+                hostName() AS host,
+                toString(event_time_microseconds) AS time,
+                query_id,
+                toString(thread_id) AS thread_id,
+                toString(trace_type) AS trace_type,
+                toString(size) AS size,
+                toString(trace) AS trace
+            FROM {dbtable}
+            WHERE
+                    event_date >= toDate(start_time_) AND event_time >= toDateTime(start_time_) AND event_time_microseconds >= start_time_
+                AND event_date <= toDate(end_time_)   AND event_time <= toDateTime(end_time_)   AND event_time_microseconds <= end_time_
+                AND query_id IN ('{query_ids}')
+            ORDER BY event_time_microseconds
+            "#,
+                start = start_microseconds
+                    .timestamp_nanos_opt()
+                    .ok_or(Error::msg("Invalid start time"))?,
+                end = match end_microseconds {
+                    Some(time) => format!(
+                        "fromUnixTimestamp64Nano({})",
+                        time.timestamp_nanos_opt()
+                            .ok_or(Error::msg("Invalid end time"))?
+                    ),
+                    None => "toDateTime64(now(), 6)".to_string(),
+                },
+                query_ids = escape_strings(query_ids),
+            ))
+            .await;
+    }
+
+    /// Exception and stack trace of the failed query (from system.query_log)
+    pub async fn get_query_exception(
+        &self,
+        query_id: &str,
+        start_microseconds: DateTime<Local>,
+        end_microseconds: Option<DateTime<Local>>,
+    ) -> Result<Columns> {
+        let dbtable = self.get_table_name("system.query_log");
+        return self
+            .execute(&format!(
+                r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_time_,
+                {end} AS end_time_
+            SELECT exception, stack_trace
+            FROM {dbtable}
+            WHERE
+                    event_date >= toDate(start_time_) AND event_time >= toDateTime(start_time_)
+                AND event_date <= toDate(end_time_)   AND event_time <= toDateTime(end_time_)
+                AND query_id = '{query_id}'
+                AND exception != ''
+            LIMIT 1
+            "#,
+                start = start_microseconds
+                    .timestamp_nanos_opt()
+                    .ok_or(Error::msg("Invalid start time"))?,
+                end = match end_microseconds {
+                    Some(time) => format!(
+                        "fromUnixTimestamp64Nano({})",
+                        time.timestamp_nanos_opt()
+                            .ok_or(Error::msg("Invalid end time"))?
+                    ),
+                    None => "toDateTime64(now(), 6)".to_string(),
+                },
+            ))
+            .await;
+    }
+
+    /// Temporary and external tables (external data sent by the client) used by the query (from
+    /// system.query_log).
+    ///
+    /// NOTE: they are shown by internal names (_tmp_<uuid>), and sizes are not available, since
+    /// such tables are visible only in the session of the query
+    pub async fn get_query_temporary_tables(
+        &self,
+        query_id: &str,
+        start_microseconds: DateTime<Local>,
+        end_microseconds: Option<DateTime<Local>>,
+    ) -> Result<Columns> {
+        let dbtable = self.get_table_name("system.query_log");
+        return self
+            .execute(&format!(
+                r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_time_,
+                {end} AS end_time_
+            SELECT arrayFilter(t -> startsWith(t, '_temporary_and_external_tables.'), tables) AS temporary_tables
+            FROM {dbtable}
+            WHERE
+                    event_date >= toDate(start_time_) AND event_time >= toDateTime(start_time_)
+                AND event_date <= toDate(end_time_)   AND event_time <= toDateTime(end_time_)
+                AND query_id = '{query_id}'
+            ORDER BY event_time_microseconds DESC
+            LIMIT 1
+            "#,
+                start = start_microseconds
+                    .timestamp_nanos_opt()
+                    .ok_or(Error::msg("Invalid start time"))?,
+                end = match end_microseconds {
+                    Some(time) => format!(
+                        "fromUnixTimestamp64Nano({})",
+                        time.timestamp_nanos_opt()
+                            .ok_or(Error::msg("Invalid end time"))?
+                    ),
+                    None => "toDateTime64(now(), 6)".to_string(),
+                },
+            ))
+            .await;
+    }
+
+    /// Slowest processors of the queries (from system.processors_profile_log)
+    pub async fn get_query_slowest_processors(
+        &self,
+        query_ids: &[String],
+        start_microseconds: DateTime<Local>,
+        end_microseconds: Option<DateTime<Local>>,
+        limit: u64,
+    ) -> Result<Columns> {
+        let dbtable = self.get_table_name("system.processors_profile_log");
+        return self
+            .execute(&format!(
+                r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_time_,
+                fromUnixTimestamp64Nano({end}) AS end_time_
+            SELECT
+                name,
+                count() AS count,
+                sum(elapsed_us)/1e6 AS elapsed_sec,
+                sum(input_wait_elapsed_us)/1e6 AS input_wait_sec,
+                sum(output_wait_elapsed_us)/1e6 AS output_wait_sec
+            FROM {dbtable}
+            WHERE
+                    event_date >= toDate(start_time_) AND event_time >  toDateTime(start_time_) AND event_time_microseconds > start_time_
+                AND event_date <= toDate(end_time_)   AND event_time <= toDateTime(end_time_)   AND event_time_microseconds <= end_time_
+                AND query_id IN ('{query_ids}')
+            GROUP BY name
+            ORDER BY elapsed_sec DESC
+            LIMIT {limit}
+            "#,
+                start = start_microseconds
+                    .timestamp_nanos_opt()
+                    .ok_or(Error::msg("Invalid start time"))?,
+                end = end_microseconds
+                    .unwrap_or(Local::now())
+                    .timestamp_nanos_opt()
+                    .ok_or(Error::msg("Invalid end time"))?,
+                query_ids = escape_strings(query_ids),
+            ))
+            .await;
+    }
+
+    /// Sampled allocations of the queries (allocation_size, count), see memory_profiler_sample_probability.
+    pub async fn get_query_allocations(
+        &self,
+        query_ids: &[String],
+        start_microseconds: DateTime<Local>,
+        end_microseconds: Option<DateTime<Local>>,
+    ) -> Result<Columns> {
+        let dbtable = self.get_table_name("system.trace_log");
+        return self
+            .execute(&format!(
+                r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_time_,
+                {end} AS end_time_
+            SELECT
+                toUInt64(size) AS allocation_size,
+                count() AS count
+            FROM {dbtable}
+            WHERE
+                    event_date >= toDate(start_time_) AND event_time >= toDateTime(start_time_) AND event_time_microseconds >= start_time_
+                AND event_date <= toDate(end_time_)   AND event_time <= toDateTime(end_time_)   AND event_time_microseconds <= end_time_
+                /* toString() to avoid errors for servers without JemallocSample */
+                AND toString(trace_type) IN ('MemorySample', 'JemallocSample')
+                /* only allocations */
+                AND size > 0
+                AND query_id IN ('{query_ids}')
+            GROUP BY allocation_size
+            "#,
+                start = start_microseconds
+                    .timestamp_nanos_opt()
+                    .ok_or(Error::msg("Invalid start time"))?,
+                end = match end_microseconds {
+                    Some(time) => format!(
+                        "fromUnixTimestamp64Nano({})",
+                        time.timestamp_nanos_opt()
+                            .ok_or(Error::msg("Invalid end time"))?
+                    ),
+                    None => "toDateTime64(now(), 6)".to_string(),
+                },
+                query_ids = escape_strings(query_ids),
+            ))
+            .await;
+    }
+
+    /// Number of threads that had Real samples in system.trace_log per time bucket (bucket is
+    /// the number of bucket_us intervals since start).
+    pub async fn get_query_threads_timeline(
+        &self,
+        query_ids: &[String],
+        start_microseconds: DateTime<Local>,
+        end_microseconds: DateTime<Local>,
+        bucket_us: u64,
+    ) -> Result<Columns> {
+        let dbtable = self.get_table_name("system.trace_log");
+        return self
+            .execute(&format!(
+                r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_time_,
+                fromUnixTimestamp64Nano({end}) AS end_time_
+            SELECT
+                toUInt64(intDiv(toUnixTimestamp64Micro(event_time_microseconds) - toUnixTimestamp64Micro(start_time_), {bucket_us})) AS bucket,
+                toUInt64(uniqExact(hostName(), thread_id)) AS threads
+            FROM {dbtable}
+            WHERE
+                    event_date >= toDate(start_time_) AND event_time >= toDateTime(start_time_) AND event_time_microseconds >= start_time_
+                AND event_date <= toDate(end_time_)   AND event_time <= toDateTime(end_time_)   AND event_time_microseconds <= end_time_
+                AND trace_type = 'Real'
+                AND query_id IN ('{query_ids}')
+            GROUP BY bucket
+            ORDER BY bucket
+            "#,
+                start = start_microseconds
+                    .timestamp_nanos_opt()
+                    .ok_or(Error::msg("Invalid start time"))?,
+                end = end_microseconds
+                    .timestamp_nanos_opt()
+                    .ok_or(Error::msg("Invalid end time"))?,
+                query_ids = escape_strings(query_ids),
+            ))
+            .await;
+    }
+
     pub async fn get_live_query_flamegraph(&self, query_ids: &[String]) -> Result<Columns> {
         let dbtable = self.get_table_name("system.stack_trace");
         return self
@@ -831,42 +1523,741 @@ impl ClickHouse {
             SETTINGS allow_introspection_functions=1
             "#,
                 dbtable,
-                query_ids.join("','"),
+                escape_strings(query_ids),
+            ))
+            .await;
+    }
+
+    /// Query for in-flight merges and mutations of the specific table (suitable for
+    /// QueryResultView, so it could be refreshed periodically).
+    pub fn get_table_merges_and_mutations_query(&self, database: &str, table: &str) -> String {
+        let (database, table) = (escape_string(database), escape_string(table));
+        let host = if self.options.cluster.is_some() {
+            "hostName() AS host,"
+        } else {
+            ""
+        };
+        return format!(
+            r#"
+            SELECT
+                {host}
+                'merge' AS type,
+                result_part_name AS name,
+                elapsed,
+                progress,
+                num_parts::UInt64 AS parts,
+                memory_usage::UInt64 AS memory,
+                '' AS reason
+            FROM {merges}
+            WHERE database = '{database}' AND table = '{table}'
+            UNION ALL
+            SELECT
+                {host}
+                'mutation' AS type,
+                mutation_id AS name,
+                dateDiff('second', create_time, now())::Float64 AS elapsed,
+                0::Float64 AS progress,
+                parts_to_do::UInt64 AS parts,
+                0::UInt64 AS memory,
+                latest_fail_reason AS reason
+            FROM {mutations}
+            WHERE database = '{database}' AND table = '{table}' AND NOT is_done
+            "#,
+            merges = self.get_table_name("system.merges"),
+            mutations = self.get_table_name("system.mutations"),
+        );
+    }
+
+    /// Per-column compressed/uncompressed size (from active parts) and compression codec for
+    /// the table.
+    pub fn get_table_columns_compression_query(&self, database: &str, table: &str) -> String {
+        let (database, table) = (escape_string(database), escape_string(table));
+        return format!(
+            r#"
+            SELECT
+                column,
+                any(type) AS type,
+                any(codec) AS codec,
+                sum(column_data_compressed_bytes) AS compressed,
+                sum(column_data_uncompressed_bytes) AS uncompressed,
+                toFloat64(if(compressed > 0, round(uncompressed / compressed, 2), 0)) AS ratio
+            FROM {parts_columns}
+            LEFT JOIN
+            (
+                SELECT name AS column, compression_codec AS codec
+                FROM system.columns
+                WHERE database = '{database}' AND table = '{table}'
+            ) AS columns USING (column)
+            WHERE database = '{database}' AND table = '{table}' AND active
+            GROUP BY column
+            "#,
+            parts_columns = self.get_table_name("system.parts_columns"),
+        );
+    }
+
+    /// Segments of the filesystem cache for the files of the table (object storage disks only).
+    pub fn get_table_filesystem_cache_query(&self, database: &str, table: &str) -> String {
+        let (database, table) = (escape_string(database), escape_string(table));
+        // NOTE: local_path of the remote disks contains table UUID (Atomic database) in the path
+        return format!(
+            r#"
+            WITH (SELECT toString(uuid) FROM system.tables WHERE database = '{database}' AND name = '{table}') AS uuid_
+            SELECT
+                local_path AS file,
+                file_segment_range_begin AS begin,
+                file_segment_range_end AS end,
+                size,
+                state,
+                cache_hits AS hits,
+                references
+            FROM system.filesystem_cache
+            INNER JOIN
+            (
+                SELECT local_path, arrayJoin(cache_paths) AS cache_path
+                FROM system.remote_data_paths
+                WHERE position(local_path, uuid_) > 0
+            ) AS paths USING (cache_path)
+            "#,
+        );
+    }
+
+    /// Replication status of the table on each replica.
+    pub async fn get_table_replicas_status(&self, database: &str, table: &str) -> Result<Columns> {
+        let (database, table) = (escape_string(database), escape_string(table));
+        return self
+            .execute(&format!(
+                r#"
+                SELECT
+                    hostName() AS host,
+                    replica_name,
+                    NOT is_readonly AND NOT is_session_expired AS is_active,
+                    log_pointer,
+                    log_max_index,
+                    queue_size,
+                    absolute_delay
+                FROM {replicas}
+                WHERE database = '{database}' AND table = '{table}'
+                ORDER BY host
+                "#,
+                replicas = self.get_table_name("system.replicas"),
+            ))
+            .await;
+    }
+
+    pub async fn has_system_table(&self, table: &str) -> Result<bool> {
+        let block = self
+            .execute(&format!(
+                "SELECT count() AS count FROM system.tables WHERE database = 'system' AND name = '{}'",
+                escape_string(table)
+            ))
+            .await?;
+        return Ok(block.get::<u64, _>(0, "count")? > 0);
+    }
+
+    /// Source parts of the merge and number of active parts in the table/partition of the merge.
+    pub async fn get_merge_impact(
+        &self,
+        host: Option<&str>,
+        database: &str,
+        table: &str,
+        part: &str,
+    ) -> Result<Columns> {
+        let (database, table, part) = (
+            escape_string(database),
+            escape_string(table),
+            escape_string(part),
+        );
+        // In --cluster mode system tables are queried from all hosts, so limit to the merge host
+        let host_filter = host
+            .map(|host| format!(" AND hostName() = '{}'", escape_string(host)))
+            .unwrap_or_default();
+        return self
+            .execute(&format!(
+                r#"
+            SELECT
+                num_parts::UInt64 AS num_parts,
+                source_part_names,
+                partition_id,
+                total_size_bytes_compressed,
+                progress,
+                (
+                    SELECT count()
+                    FROM {parts}
+                    WHERE database = '{database}' AND table = '{table}' AND active{host_filter}
+                ) AS table_active_parts,
+                (
+                    SELECT count()
+                    FROM {parts}
+                    WHERE database = '{database}' AND table = '{table}' AND active{host_filter}
+                        AND partition_id = (
+                            SELECT any(partition_id)
+                            FROM {merges}
+                            WHERE database = '{database}' AND table = '{table}' AND result_part_name = '{part}'{host_filter}
+                        )
+                ) AS partition_active_parts
+            FROM {merges}
+            WHERE database = '{database}' AND table = '{table}' AND result_part_name = '{part}'{host_filter}
+            LIMIT 1
+            "#,
+                parts = self.get_table_name("system.parts"),
+                merges = self.get_table_name("system.merges"),
+            ))
+            .await;
+    }
+
+    /// Children of the ZooKeeper node (system.zookeeper is the same for all hosts, so it is not
+    /// queried from the whole cluster)
+    pub fn get_zookeeper_query(&self, path: &str) -> String {
+        return format!(
+            r#"
+            SELECT
+                name,
+                numChildren AS children,
+                ctime,
+                mtime,
+                value AS _value
+            FROM system.zookeeper
+            WHERE path = '{}'
+            "#,
+            escape_string(path),
+        );
+    }
+
+    /// Events from system.part_log for the time interval (newest first)
+    pub fn get_part_log_timeline_query(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<String> {
+        let start = start
+            .timestamp_nanos_opt()
+            .ok_or(Error::msg("Invalid start"))?;
+        let end = end.timestamp_nanos_opt().ok_or(Error::msg("Invalid end"))?;
+        let host = if self.options.cluster.is_some() {
+            "hostName() AS host,"
+        } else {
+            ""
+        };
+        return Ok(format!(
+            r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_,
+                fromUnixTimestamp64Nano({end})   AS end_
+            SELECT
+                {host}
+                event_time,
+                event_type::String AS type,
+                database,
+                table,
+                part_name AS part,
+                rows,
+                size_in_bytes AS size,
+                duration_ms,
+                error::UInt32 AS error,
+                exception
+            FROM {part_log}
+            WHERE
+                event_date BETWEEN toDate(start_) AND toDate(end_) AND
+                event_time BETWEEN toDateTime(start_) AND toDateTime(end_)
+            "#,
+            part_log = self.get_table_name("system.part_log"),
+        ));
+    }
+
+    /// Durations of the finished queries with the same normalized query (statistics and sample
+    /// of durations for the histogram)
+    pub async fn get_query_durations(
+        &self,
+        query: &str,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<Columns> {
+        let start = start
+            .timestamp_nanos_opt()
+            .ok_or(Error::msg("Invalid start"))?;
+        let end = end.timestamp_nanos_opt().ok_or(Error::msg("Invalid end"))?;
+        return self
+            .execute(&format!(
+                r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_,
+                fromUnixTimestamp64Nano({end})   AS end_,
+                normalizedQueryHash('{query}') AS normalized_query_hash_
+            SELECT
+                count() AS count,
+                min(query_duration_ms) AS min_ms,
+                max(query_duration_ms) AS max_ms,
+                quantiles(0.5, 0.9, 0.99)(query_duration_ms) AS quantiles_ms,
+                groupArraySample(10000)(query_duration_ms) AS durations_ms
+            FROM {query_log}
+            WHERE
+                event_date BETWEEN toDate(start_) AND toDate(end_) AND
+                event_time BETWEEN toDateTime(start_) AND toDateTime(end_) AND
+                type = 'QueryFinish' AND
+                normalized_query_hash = normalized_query_hash_
+            "#,
+                query = escape_string(query),
+                query_log = self.get_table_name("system.query_log"),
+            ))
+            .await;
+    }
+
+    /// Merges/mutations from system.part_log that finished in the time interval (start/end in
+    /// seconds, for the timeline).
+    pub async fn get_merges_timeline(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<Columns> {
+        let start = start
+            .timestamp_nanos_opt()
+            .ok_or(Error::msg("Invalid start"))?;
+        let end = end.timestamp_nanos_opt().ok_or(Error::msg("Invalid end"))?;
+        let host = if self.options.cluster.is_some() {
+            "hostName() || ':' ||"
+        } else {
+            ""
+        };
+        return self
+            .execute(&format!(
+                r#"
+            WITH
+                fromUnixTimestamp64Nano({start}) AS start_,
+                fromUnixTimestamp64Nano({end})   AS end_
+            SELECT
+                {host} database || '.' || table AS table,
+                event_type::String AS type,
+                -- UInt64 - UInt64 is Int64, while start_time is read as UInt64
+                toUInt64(greatest(toInt64(toUnixTimestamp(event_time)) - intDiv(duration_ms, 1000), 0)) AS start_time,
+                toUInt64(toUnixTimestamp(event_time)) AS end_time
+            FROM {part_log}
+            WHERE
+                event_date BETWEEN toDate(start_) AND toDate(end_) AND
+                event_time BETWEEN toDateTime(start_) AND toDateTime(end_) AND
+                event_type IN ('MergeParts', 'MutatePart')
+            "#,
+                part_log = self.get_table_name("system.part_log"),
+            ))
+            .await;
+    }
+
+    /// Events from system.part_log for the part (including merges the part participated in).
+    pub fn get_part_log_query(&self, database: &str, table: &str, part: &str) -> String {
+        let (database, table, part) = (
+            escape_string(database),
+            escape_string(table),
+            escape_string(part),
+        );
+        let host = if self.options.cluster.is_some() {
+            "hostName() AS host,"
+        } else {
+            ""
+        };
+        return format!(
+            r#"
+            SELECT
+                {host}
+                event_time,
+                event_type::String AS type,
+                part_name AS name,
+                duration_ms,
+                rows,
+                size_in_bytes AS size,
+                arrayStringConcat(merged_from, ' ') AS merged_from,
+                exception
+            FROM {part_log}
+            WHERE database = '{database}' AND table = '{table}' AND (part_name = '{part}' OR has(merged_from, '{part}'))
+            "#,
+            part_log = self.get_table_name("system.part_log"),
+        );
+    }
+
+    /// Query for the dashboard, one row per signal (worst value across all hosts), with
+    /// severity 0 (OK), 1 (WARN) or 2 (CRIT).
+    pub fn get_dashboard_query(&self) -> String {
+        return format!(
+            r#"
+            SELECT
+                signal,
+                value,
+                multiIf(severity = 2, 'CRIT', severity = 1, 'WARN', 'OK') AS status,
+                severity
+            FROM
+            (
+                SELECT
+                    'Replication lag' AS signal,
+                    concat(toString(max(absolute_delay)), ' sec') AS value,
+                    toUInt8(multiIf(max(absolute_delay) > 300, 2, max(absolute_delay) > 60, 1, 0)) AS severity
+                FROM {replicas}
+                UNION ALL
+                SELECT
+                    'Failed mutations' AS signal,
+                    toString(count()) AS value,
+                    toUInt8(if(count() > 0, 2, 0)) AS severity
+                FROM {mutations}
+                WHERE NOT is_done AND latest_fail_reason != ''
+                UNION ALL
+                SELECT
+                    'Stuck merges' AS signal,
+                    concat(toString(count()), ' (> 1 hour)') AS value,
+                    toUInt8(if(count() > 0, 1, 0)) AS severity
+                FROM {merges}
+                WHERE elapsed > 3600
+                UNION ALL
+                SELECT
+                    'Recent errors' AS signal,
+                    concat(toString(count()), ' error codes (last 10 min)') AS value,
+                    toUInt8(if(count() > 0, 1, 0)) AS severity
+                FROM {errors}
+                WHERE last_error_time > now() - INTERVAL 10 MINUTE
+                UNION ALL
+                SELECT
+                    'Memory usage' AS signal,
+                    concat(toString(round(max(usage), 2)), ' %') AS value,
+                    toUInt8(multiIf(max(usage) > 90, 2, max(usage) > 80, 1, 0)) AS severity
+                FROM
+                (
+                    SELECT
+                        hostName() AS host,
+                        sumIf(value, metric = 'MemoryResident') AS resident,
+                        sumIf(value, metric = 'OSMemoryTotal') AS total,
+                        if(total > 0, resident / total * 100, 0) AS usage
+                    FROM {asynchronous_metrics}
+                    GROUP BY host
+                )
+            )
+            "#,
+            replicas = self.get_table_name("system.replicas"),
+            mutations = self.get_table_name("system.mutations"),
+            merges = self.get_table_name("system.merges"),
+            errors = self.get_table_name("system.errors"),
+            asynchronous_metrics = self.get_table_name("system.asynchronous_metrics"),
+        );
+    }
+
+    /// Settings profiles of the user (assigned directly, and inherited one level deeper) with
+    /// their settings/constraints.
+    pub async fn get_settings_profile_elements(&self, user: &str) -> Result<Columns> {
+        return self
+            .execute(&format!(
+                r#"
+                WITH user_profiles AS
+                (
+                    SELECT inherit_profile
+                    FROM system.settings_profile_elements
+                    WHERE user_name = '{user}' AND inherit_profile IS NOT NULL
+                )
+                SELECT
+                    ifNull(profile_name, '') AS profile,
+                    ifNull(inherit_profile, '') AS inherit_profile,
+                    ifNull(setting_name, '') AS setting_name,
+                    ifNull(value, '') AS value,
+                    ifNull(min, '') AS min,
+                    ifNull(max, '') AS max,
+                    ifNull(toString(writability), '') AS writability
+                FROM system.settings_profile_elements
+                WHERE
+                    user_name = '{user}' OR
+                    profile_name IN user_profiles OR
+                    profile_name IN (
+                        SELECT inherit_profile
+                        FROM system.settings_profile_elements
+                        WHERE profile_name IN user_profiles AND inherit_profile IS NOT NULL
+                    )
+                ORDER BY profile, setting_name
+                "#,
+                user = escape_string(user),
             ))
             .await;
     }
 
+    /// Probe capabilities that are required by chdig features (cheap queries), returns (what,
+    /// error if any)
+    pub async fn check_permissions(&self) -> Vec<(&'static str, Option<String>)> {
+        let probes = [
+            (
+                "SELECT FROM system.processes",
+                "SELECT * FROM system.processes LIMIT 0",
+            ),
+            (
+                "SELECT FROM system.query_log",
+                "SELECT * FROM system.query_log LIMIT 0",
+            ),
+            (
+                "SELECT FROM system.trace_log",
+                "SELECT * FROM system.trace_log LIMIT 0",
+            ),
+            (
+                "SELECT FROM system.text_log",
+                "SELECT * FROM system.text_log LIMIT 0",
+            ),
+            (
+                "SELECT FROM system.part_log",
+                "SELECT * FROM system.part_log LIMIT 0",
+            ),
+            (
+                "Introspection functions (flamegraphs)",
+                "SELECT addressToSymbol(toUInt64(0)) SETTINGS allow_introspection_functions=1",
+            ),
+            // TEST does not kill anything, but fails in readonly mode
+            ("KILL QUERY", "KILL QUERY WHERE 0 TEST"),
+        ];
+        let mut result = Vec::new();
+        for (what, query) in probes {
+            let error = self.execute(query).await.err().map(|e| e.to_string());
+            result.push((what, error));
+        }
+        return result;
+    }
+
     pub async fn execute(&self, query: &str) -> Result<Columns> {
-        return Ok(self
-            .pool
-            .get_handle()
-            .await?
-            .query(query)
-            .fetch_all()
-            .await?);
+        let result = retry_on_connection_loss(
+            query,
+            RECONNECT_BACKOFF,
+            |attempt, err| self.on_connection_loss(attempt, err),
+            move || async move {
+                return Ok(self
+                    .pool
+                    .get_handle()
+                    .await?
+                    .query(query)
+                    .fetch_all()
+                    .await?);
+            },
+        )
+        .await;
+        return self.check_ssh_tunnel(result);
     }
 
     async fn execute_simple(&self, query: &str) -> Result<()> {
-        let mut client = self.pool.get_handle().await?;
-        let mut stream = client.query(query).stream_blocks();
-        let ret = stream.next().await;
-        if let Some(Err(err)) = ret {
-            return Err(Error::new(err));
-        } else {
-            return Ok(());
+        let result = retry_on_connection_loss(
+            query,
+            RECONNECT_BACKOFF,
+            |attempt, err| self.on_connection_loss(attempt, err),
+            move || async move {
+                let mut client = self.pool.get_handle().await?;
+                let mut stream = client.query(query).stream_blocks();
+                let ret = stream.next().await;
+                if let Some(Err(err)) = ret {
+                    return Err(Error::new(err));
+                } else {
+                    return Ok(());
+                }
+            },
+        )
+        .await;
+        return self.check_ssh_tunnel(result);
+    }
+
+    // Called before the next attempt to reconnect (after connection loss)
+    fn on_connection_loss(&self, attempt: u32, err: &Error) -> Result<()> {
+        // Retries are pointless if the tunnel is gone, report it instead
+        if let Some(tunnel) = &self.ssh_tunnel {
+            tunnel.check()?;
         }
+        let message = format!(
+            "Connection lost ({}), reconnecting ({}/{})...",
+            err, attempt, RECONNECT_RETRIES
+        );
+        log::warn!("{}", message);
+        if let Some(callback) = self.on_reconnect.lock().unwrap().as_ref() {
+            callback(message);
+        }
+        return Ok(());
+    }
+
+    // Replace connection error with the error of the tunnel (if it is gone)
+    fn check_ssh_tunnel<T>(&self, result: Result<T>) -> Result<T> {
+        if let (Err(err), Some(tunnel)) = (&result, &self.ssh_tunnel) {
+            if is_connection_error(err) {
+                tunnel.check()?;
+            }
+        }
+        return result;
     }
 
     pub fn get_table_name(&self, dbtable: &str) -> String {
-        let cluster = self
-            .options
-            .cluster
-            .as_ref()
-            .unwrap_or(&"".to_string())
-            .clone();
-        if cluster.is_empty() {
-            return dbtable.to_string();
+        return get_table_name(&self.options, dbtable);
+    }
+}
+
+// Should the system.*_log table include history (i.e. system.query_log_0) or not.
+fn use_history(options: &ClickHouseOptions, database: &str, table: &str) -> bool {
+    if database != "system" || !table.ends_with("_log") {
+        return false;
+    }
+    // Per-table configuration overrides --history
+    if let Some(history_tables) = &options.history_tables {
+        return history_tables
+            .iter()
+            .any(|t| t.trim().trim_start_matches("system.") == table);
+    }
+    return options.history;
+}
+
+fn get_table_name(options: &ClickHouseOptions, dbtable: &str) -> String {
+    let dbtable = match dbtable.split_once('.') {
+        Some((database, table)) if use_history(options, database, table) => {
+            format!("merge('{}', '^{}(_[0-9]+)?$')", database, table)
+        }
+        _ => dbtable.to_string(),
+    };
+
+    let cluster = options.cluster.as_ref().unwrap_or(&"".to_string()).clone();
+    if cluster.is_empty() {
+        return dbtable;
+    }
+    return format!("clusterAllReplicas('{}', {})", cluster, dbtable);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::options::ChDigOptions;
+    use clap::Parser;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Simulates a handle that had been dropped by the server (i.e. on restart) for the first
+    // `failures` attempts
+    async fn run_with_dropped_handle(query: &str, failures: u32) -> (Result<u32>, u32) {
+        let calls = &AtomicU32::new(0);
+        let result = retry_on_connection_loss(
+            query,
+            Duration::ZERO,
+            |_, _| Ok(()),
+            move || async move {
+                let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                if call <= failures {
+                    return Err(Error::new(DriverCrateError::Io(io::Error::new(
+                        io::ErrorKind::ConnectionReset,
+                        "connection reset by peer",
+                    ))));
+                }
+                return Ok(call);
+            },
+        )
+        .await;
+        return (result, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_retry_select_on_dropped_handle() {
+        let (result, calls) = run_with_dropped_handle("SELECT 1", 2).await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let (result, calls) = run_with_dropped_handle("SELECT 1", 10).await;
+        assert!(result.is_err());
+        assert_eq!(calls, RECONNECT_RETRIES + 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_for_non_idempotent_queries() {
+        for query in [
+            "KILL QUERY WHERE query_id = 'foo'",
+            "OPTIMIZE TABLE db.t FINAL",
+            "ALTER TABLE db.t ATTACH PART 'all_1_1_0'",
+            "SYSTEM STOP MERGES db.t",
+        ] {
+            let (result, calls) = run_with_dropped_handle(query, 1).await;
+            assert!(result.is_err(), "{}", query);
+            assert_eq!(calls, 1, "{}", query);
         }
-        return format!("clusterAllReplicas('{}', {})", cluster, dbtable);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_on_timeout() {
+        let calls = &AtomicU32::new(0);
+        let result: Result<()> = retry_on_connection_loss(
+            "SELECT 1",
+            Duration::ZERO,
+            |_, _| Ok(()),
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                return Err(Error::new(DriverCrateError::Driver(
+                    clickhouse_rs::errors::DriverError::Timeout,
+                )));
+            },
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    fn clickhouse_options(args: &[&str]) -> ClickHouseOptions {
+        let options = ChDigOptions::try_parse_from([&["chdig"], args].concat()).unwrap();
+        return options.clickhouse;
+    }
+
+    #[test]
+    fn test_get_table_name_history() {
+        let options = clickhouse_options(&[]);
+        assert_eq!(
+            get_table_name(&options, "system.query_log"),
+            "system.query_log"
+        );
+
+        let options = clickhouse_options(&["--history"]);
+        assert_eq!(
+            get_table_name(&options, "system.query_log"),
+            "merge('system', '^query_log(_[0-9]+)?$')"
+        );
+        // Only system.*_log tables has history
+        assert_eq!(get_table_name(&options, "system.parts"), "system.parts");
+        assert_eq!(get_table_name(&options, "db.query_log"), "db.query_log");
+    }
+
+    #[test]
+    fn test_get_table_name_history_tables() {
+        // --history-tables overrides --history
+        let options = clickhouse_options(&["--history", "--history-tables", "system.part_log"]);
+        assert!(use_history(&options, "system", "part_log"));
+        assert!(!use_history(&options, "system", "query_log"));
+        assert_eq!(
+            get_table_name(&options, "system.part_log"),
+            "merge('system', '^part_log(_[0-9]+)?$')"
+        );
+        assert_eq!(
+            get_table_name(&options, "system.query_log"),
+            "system.query_log"
+        );
+
+        let options = clickhouse_options(&["--history-tables", "query_log, text_log"]);
+        assert!(use_history(&options, "system", "query_log"));
+        assert!(use_history(&options, "system", "text_log"));
+        assert!(!use_history(&options, "system", "part_log"));
+    }
+
+    #[test]
+    fn test_get_table_name_cluster() {
+        let options = clickhouse_options(&["--cluster", "c", "--history"]);
+        assert_eq!(
+            get_table_name(&options, "system.query_log"),
+            "clusterAllReplicas('c', merge('system', '^query_log(_[0-9]+)?$'))"
+        );
+    }
+
+    #[test]
+    fn test_escape_string() {
+        assert_eq!(escape_string("foo"), "foo");
+        assert_eq!(escape_string("it's"), "it\\'s");
+        assert_eq!(escape_string("a\\'b"), "a\\\\\\'b");
+        assert_eq!(
+            escape_strings(&["a".to_string(), "b'".to_string()]),
+            "a','b\\'"
+        );
+        assert_eq!(get_queries_filter("~it's", &None), "match(query, 'it\\'s')");
+    }
+
+    #[test]
+    fn test_is_read_only_query() {
+        assert!(is_read_only_query("SELECT 1"));
+        assert!(is_read_only_query("  (select 1)"));
+        assert!(is_read_only_query("WITH 1 AS x SELECT x"));
+        assert!(!is_read_only_query("KILL QUERY WHERE 1"));
+        assert!(!is_read_only_query("DETACH TABLE t"));
     }
 }
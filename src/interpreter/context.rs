@@ -1,8 +1,10 @@
 use crate::interpreter::{options::ChDigOptions, ClickHouse, Worker};
+use crate::view::Navigation;
 use anyhow::Result;
 use chdig::ActionDescription;
-use chrono::Duration;
-use cursive::{event::Event, event::EventResult, views::Dialog, views::OnEventView, Cursive, View};
+use chrono::{DateTime, Duration, Local};
+use cursive::{event::Event, event::EventResult, views::OnEventView, Cursive, View};
+use std::collections::HashMap;
 use std::sync::{Arc, Condvar, Mutex};
 
 pub type ContextArc = Arc<Mutex<Context>>;
@@ -20,6 +22,42 @@ pub struct ViewAction {
     pub callback: ViewActionCallback,
 }
 
+/// Actions of the current view (only one view has actions at a time)
+#[derive(Default)]
+pub struct ViewActions {
+    pub actions: Vec<ViewAction>,
+    // Name of the view that owns actions (to avoid removing actions of another view)
+    owner: Option<&'static str>,
+}
+
+impl ViewActions {
+    /// Start registering actions for the view (actions of the previous view are removed)
+    pub fn begin(&mut self, view_name: &'static str) {
+        log::debug!("Adding views actions for {}", view_name);
+        self.actions.clear();
+        self.owner = Some(view_name);
+    }
+
+    /// Remove actions of the view, unless they had been already replaced by another view
+    pub fn remove(&mut self, view_name: &'static str) {
+        if self.owner != Some(view_name) {
+            log::debug!(
+                "Not removing views actions for {} (owned by {:?})",
+                view_name,
+                self.owner
+            );
+            return;
+        }
+        log::debug!("Removing views actions for {}", view_name);
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.actions.clear();
+        self.owner = None;
+    }
+}
+
 pub struct Context {
     pub options: ChDigOptions,
 
@@ -32,14 +70,33 @@ pub struct Context {
 
     pub global_actions: Vec<GlobalAction>,
     pub views_menu_actions: Vec<GlobalAction>,
-    pub view_actions: Vec<ViewAction>,
+    pub view_actions: ViewActions,
 
     pub pending_view_callback: Option<ViewActionCallback>,
+
+    // Sort ProfileEvents by name (instead of value) in the query details (for the session)
+    pub profile_events_sort_by_name: bool,
+
+    // Last successful update of the views (by view name), shown in the status bar
+    pub last_updates: HashMap<&'static str, DateTime<Local>>,
+}
+
+/// Show reconnect attempts (see ClickHouse::set_on_reconnect()) in the status bar
+pub fn reconnect_status_callback(cb_sink: cursive::CbSink) -> impl Fn(String) + Send + Sync {
+    return move |message: String| {
+        cb_sink
+            .send(Box::new(move |siv: &mut Cursive| {
+                siv.set_statusbar_content(message);
+            }))
+            // Ignore errors on exit
+            .unwrap_or_default();
+    };
 }
 
 impl Context {
     pub async fn new(options: ChDigOptions, cb_sink: cursive::CbSink) -> Result<ContextArc> {
         let clickhouse = Arc::new(ClickHouse::new(options.clickhouse.clone()).await?);
+        clickhouse.set_on_reconnect(reconnect_status_callback(cb_sink.clone()));
         let server_version = clickhouse.version();
         let worker = Worker::new();
         let background_runner_cv = Arc::new((Mutex::new(()), Condvar::new()));
@@ -53,8 +110,10 @@ impl Context {
             cb_sink,
             global_actions: Vec::new(),
             views_menu_actions: Vec::new(),
-            view_actions: Vec::new(),
+            view_actions: ViewActions::default(),
             pending_view_callback: None,
+            profile_events_sort_by_name: false,
+            last_updates: HashMap::new(),
         }));
 
         context.lock().unwrap().worker.start(context.clone());
@@ -62,6 +121,18 @@ impl Context {
         return Ok(context);
     }
 
+    /// Text for the status bar (server version, the name of the connection and read-only mode)
+    pub fn connection_status(&self) -> String {
+        let status = match &self.options.clickhouse.connection {
+            Some(connection) => format!("Connected to {} ({}).", self.server_version, connection),
+            None => format!("Connected to {}.", self.server_version),
+        };
+        if self.options.clickhouse.read_only {
+            return format!("{} [read-only]", status);
+        }
+        return status;
+    }
+
     pub fn add_global_action<F, E>(
         &mut self,
         siv: &mut Cursive,
@@ -105,6 +176,20 @@ impl Context {
         self.views_menu_actions.push(action);
     }
 
+    /// Start registering actions for the view (actions of the previous view are removed)
+    pub fn begin_view_actions(&mut self, view_name: &'static str) {
+        self.view_actions.begin(view_name);
+    }
+
+    /// Remove actions of the view, unless they had been already replaced by another view
+    pub fn remove_view_actions(&mut self, view_name: &'static str) {
+        self.view_actions.remove(view_name);
+    }
+
+    pub fn clear_view_actions(&mut self) {
+        self.view_actions.clear();
+    }
+
     pub fn add_view_action<F, E, V>(
         &mut self,
         view: &mut OnEventView<V>,
@@ -128,13 +213,13 @@ impl Context {
             match result {
                 Err(err) => {
                     return Some(EventResult::with_cb_once(move |siv: &mut Cursive| {
-                        siv.add_layer(Dialog::info(err.to_string()));
+                        siv.show_action_error(err);
                     }));
                 }
                 Ok(event) => return event,
             }
         });
-        self.view_actions.push(action);
+        self.view_actions.actions.push(action);
     }
 
     pub fn add_view_action_without_shortcut<F, V>(
@@ -149,11 +234,27 @@ impl Context {
         return self.add_view_action(view, text, Event::Unknown(Vec::from([0u8])), cb);
     }
 
+    pub fn set_last_update(&mut self, view_name: &'static str) {
+        self.last_updates.insert(view_name, Local::now());
+    }
+
+    /// Time of the last update of the most recently updated view (except for the summary, that
+    /// is always shown)
+    pub fn get_last_update(&self) -> Option<(&'static str, DateTime<Local>)> {
+        return self
+            .last_updates
+            .iter()
+            .filter(|(view_name, _)| **view_name != "summary")
+            .max_by_key(|(_, time)| **time)
+            .map(|(view_name, time)| (*view_name, *time));
+    }
+
     pub fn trigger_view_refresh(&self) {
         self.background_runner_cv.1.notify_all();
     }
 
     pub fn shift_time_interval(&mut self, is_sub: bool, minutes: i64) {
+        self.options.view.end_is_now = false;
         let new_start = &mut self.options.view.start;
         let new_end = &mut self.options.view.end;
 
@@ -178,3 +279,66 @@ impl Context {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_action(view_actions: &mut ViewActions, text: &'static str) {
+        view_actions.actions.push(ViewAction {
+            description: ActionDescription {
+                text,
+                event: Event::Unknown(Vec::from([0u8])),
+            },
+            callback: Arc::new(Box::new(
+                |_: &mut dyn View| -> Result<Option<EventResult>> {
+                    return Ok(None);
+                },
+            )),
+        });
+    }
+
+    fn texts(view_actions: &ViewActions) -> Vec<&'static str> {
+        return view_actions
+            .actions
+            .iter()
+            .map(|action| action.description.text)
+            .collect();
+    }
+
+    // Like Tab in ProcessesView: the new view is created before the previous one is dropped
+    #[test]
+    fn test_switch_view_replaces_actions() {
+        let mut view_actions = ViewActions::default();
+        view_actions.begin("processes");
+        add_action(&mut view_actions, "Show query logs");
+        add_action(&mut view_actions, "Kill this query");
+
+        view_actions.begin("last_query_log");
+        add_action(&mut view_actions, "Show query logs");
+        assert_eq!(texts(&view_actions), vec!["Show query logs"]);
+
+        // Drop of the previous view should not remove actions of the new view
+        view_actions.remove("processes");
+        assert_eq!(texts(&view_actions), vec!["Show query logs"]);
+
+        view_actions.remove("last_query_log");
+        assert!(view_actions.actions.is_empty());
+    }
+
+    // Like drop_main_view(): actions are cleared, and then the previous view is dropped
+    #[test]
+    fn test_drop_main_view_clears_actions() {
+        let mut view_actions = ViewActions::default();
+        view_actions.begin("processes");
+        add_action(&mut view_actions, "Kill this query");
+
+        view_actions.clear();
+        assert!(view_actions.actions.is_empty());
+        view_actions.remove("processes");
+
+        view_actions.begin("merges");
+        add_action(&mut view_actions, "Show merge");
+        assert_eq!(texts(&view_actions), vec!["Show merge"]);
+    }
+}
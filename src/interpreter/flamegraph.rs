@@ -1,6 +1,8 @@
 use crate::interpreter::clickhouse::Columns;
 use anyhow::{Error, Result};
 use futures::channel::mpsc;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::process::{Command, Stdio};
 use tokio::time::{sleep, Duration};
 use urlencoding::encode;
@@ -36,6 +38,30 @@ pub fn show(_block: Columns) -> Result<()> {
     return Err(Error::msg("chdig compiled without flameshow support"));
 }
 
+pub fn save_svg(block: Columns, title: &str, path: &str) -> Result<()> {
+    let data = block
+        .rows()
+        .map(|x| {
+            [
+                x.get::<String, _>(0).unwrap(),
+                x.get::<u64, _>(1).unwrap().to_string(),
+            ]
+            .join(" ")
+        })
+        .collect::<Vec<String>>();
+
+    if data.is_empty() {
+        return Err(Error::msg("Flamegraph is empty"));
+    }
+
+    let mut options = inferno::flamegraph::Options::default();
+    options.title = title.to_string();
+    let mut writer = BufWriter::new(File::create(path)?);
+    inferno::flamegraph::from_lines(&mut options, data.iter().map(|x| x.as_str()), &mut writer)?;
+    writer.flush()?;
+    return Ok(());
+}
+
 pub async fn open_in_speedscope(block: Columns) -> Result<()> {
     let data = block
         .rows()
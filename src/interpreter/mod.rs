@@ -4,6 +4,7 @@ pub mod clickhouse;
 mod clickhouse_quirks;
 mod context;
 mod query_process;
+mod ssh_tunnel;
 mod worker;
 // only functions
 pub mod flamegraph;
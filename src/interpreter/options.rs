@@ -1,11 +1,15 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime};
-use clap::{builder::ArgPredicate, ArgAction, Args, CommandFactory, Parser, Subcommand};
+use chrono_tz::Tz;
+use clap::{
+    builder::ArgPredicate, parser::ValueSource, ArgAction, ArgMatches, Args, CommandFactory,
+    FromArgMatches, Parser, Subcommand, ValueEnum,
+};
 use clap_complete::{generate, Shell};
 use quick_xml::de::Deserializer as XmlDeserializer;
 use serde::Deserialize;
 use serde_yaml::Deserializer as YamlDeserializer;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::io;
@@ -66,6 +70,16 @@ pub enum ChDigViews {
     SlowQueries,
     /// Show merges for MergeTree engine (system.merges)
     Merges,
+    /// Show active parts for MergeTree engine (system.parts)
+    Parts,
+    /// Show detached parts for MergeTree engine (system.detached_parts)
+    DetachedParts,
+    /// Show disks free/used/total space (system.disks)
+    Disks,
+    /// Browse ZooKeeper (system.zookeeper)
+    ZooKeeper,
+    /// Show parts events (merges, mutations, inserts, ...) for the time interval (system.part_log)
+    PartLog,
     /// Show mutations for MergeTree engine (system.mutations)
     Mutations,
     /// Show replication queue for ReplicatedMergeTree engine (system.replication_queue)
@@ -82,6 +96,11 @@ pub enum ChDigViews {
     Dictionaries,
     /// Show server logs (system.text_log)
     ServerLogs,
+    /// Show overview of the most critical signals (replication lag, failed mutations, stuck
+    /// merges, recent errors, memory usage)
+    Dashboard,
+    /// Show only server summary (lightweight always-on dashboard)
+    Summary,
 }
 
 #[derive(Parser, Clone)]
@@ -104,11 +123,64 @@ pub struct ClickHouseOptions {
     pub url: Option<String>,
     #[arg(short('C'), long)]
     pub connection: Option<String>,
+    /// Comma separated list of hosts (host[:port], overrides the host from the URL), chdig
+    /// connects to the first available one and fails over to the next ones in order if the
+    /// current host goes down (default port is applied per host)
+    #[arg(long, value_delimiter = ',')]
+    pub host: Option<Vec<String>>,
+    /// Connect via SSH jump host (user@host[:port]), the ClickHouse host/port are forwarded to
+    /// the local port with the ssh binary (so ~/.ssh/config is respected), only the first host
+    /// is used (i.e. no failover for --host), note, that with secure=true it may require
+    /// skip_verify=true
+    #[arg(long, value_name = "user@host[:port]")]
+    pub ssh_jump: Option<String>,
     // Safe version for "url" (to show in UI)
     #[clap(skip)]
     pub url_safe: String,
     #[arg(short('c'), long)]
     pub cluster: Option<String>,
+    /// Use merge() for system.*_log tables to include history (i.e. query_log_0 tables that
+    /// are created on schema changes)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub history: bool,
+    /// Comma separated list of system.*_log tables that should use history (i.e.
+    /// query_log,part_log), overrides --history
+    #[arg(long, value_delimiter = ',')]
+    pub history_tables: Option<Vec<String>>,
+    /// Maximum number of connections to ClickHouse (excess queries will wait for a free
+    /// connection), useful for servers with tight max_concurrent_queries
+    #[arg(long, default_value_t = 5)]
+    pub max_connections: usize,
+    /// Always enable compression (skips detection of local connections, that requires DNS
+    /// resolving)
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "no_compression")]
+    pub force_compression: bool,
+    /// Never enable compression (skips detection of local connections)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub no_compression: bool,
+    /// Send "SELECT 1" if there were no queries for this interval, to keep the connection alive
+    /// (for aggressive idle connection killers) and detect connection drops early (0 - disable)
+    #[arg(long, value_parser = |arg: &str| humantime::parse_duration(arg), default_value = "30s")]
+    pub keepalive_interval: time::Duration,
+    /// Disable actions that modify anything (KILL, execute query, SYSTEM/ALTER commands), enabled
+    /// automatically for readonly users (readonly=1 in the URL)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub read_only: bool,
+    /// Enable query profiler (10ms sampling), memory profiler and processors profiling for the
+    /// queries executed by chdig (i.e. "Execute query"), so that flamegraphs work even for short
+    /// queries (NOTE: adds some overhead on the server)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub profile: bool,
+    /// Comma separated list of columns for the queries filter (i.e. query_id, since searching
+    /// in query may be slow on busy servers), default - all of them
+    #[arg(long, value_delimiter = ',', value_parser = parse_filter_column)]
+    pub filter_columns: Option<Vec<String>>,
+    /// Connection timeout (overrides connection_timeout from the URL, default 5s)
+    #[arg(long, value_parser = |arg: &str| humantime::parse_duration(arg))]
+    pub connect_timeout: Option<time::Duration>,
+    /// Query timeout (overrides query_timeout from the URL, default 600s)
+    #[arg(long, value_parser = |arg: &str| humantime::parse_duration(arg))]
+    pub query_timeout: Option<time::Duration>,
 }
 
 pub fn parse_datetime_or_date(value: &str) -> Result<DateTime<Local>, String> {
@@ -144,6 +216,211 @@ pub fn parse_datetime_or_date(value: &str) -> Result<DateTime<Local>, String> {
     ));
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct AutoKillRule {
+    pub memory: Option<u64>,
+    pub elapsed: Option<time::Duration>,
+}
+
+fn parse_size(value: &str) -> Result<u64, String> {
+    let pos = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(pos);
+    let number = number
+        .parse::<f64>()
+        .map_err(|e| format!("Invalid size {}: {}", value, e))?;
+    let multiplier: u64 = match unit.to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "t" | "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return Err(format!("Unknown unit {} for {}", unit, value)),
+    };
+    return Ok((number * multiplier as f64) as u64);
+}
+
+fn parse_auto_kill_rule(value: &str) -> Result<AutoKillRule, String> {
+    let mut rule = AutoKillRule::default();
+    for threshold in value.split(',') {
+        let (name, threshold_value) = threshold.split_once('=').ok_or(format!(
+            "Invalid threshold {} (expected name=value)",
+            threshold
+        ))?;
+        match name {
+            "memory" => rule.memory = Some(parse_size(threshold_value)?),
+            "elapsed" => {
+                rule.elapsed = Some(
+                    humantime::parse_duration(threshold_value)
+                        .map_err(|e| format!("Invalid elapsed {}: {}", threshold_value, e))?,
+                )
+            }
+            _ => return Err(format!("Unknown threshold {} (memory/elapsed)", name)),
+        }
+    }
+    return Ok(rule);
+}
+
+// column=width,...
+fn parse_column_widths(value: &str) -> Result<HashMap<String, usize>, String> {
+    let mut widths = HashMap::new();
+    for column_width in value.split(',') {
+        let (column, width) = column_width.split_once('=').ok_or(format!(
+            "Invalid column width {} (expected column=width)",
+            column_width
+        ))?;
+        let width = width.parse::<usize>().map_err(|e| e.to_string())?;
+        if width == 0 {
+            return Err(format!("Width of the column {} cannot be 0", column));
+        }
+        widths.insert(column.to_string(), width);
+    }
+    return Ok(widths);
+}
+
+// Columns of the queries views that can be configured with --columns (by the name from the header)
+pub const QUERIES_COLUMNS: [&str; 18] = [
+    "query_id",
+    "cpu",
+    "io_wait",
+    "cpu_wait",
+    "user",
+    "client",
+    "thr",
+    "mem",
+    "disk",
+    "io",
+    "net",
+    "read_rows",
+    "read_bytes",
+    "progress",
+    "cache",
+    "killed",
+    "elapsed",
+    "query",
+];
+
+fn parse_column(value: &str) -> Result<String, String> {
+    if !QUERIES_COLUMNS.contains(&value) {
+        return Err(format!(
+            "Unknown column {} (valid columns: {})",
+            value,
+            QUERIES_COLUMNS.join(",")
+        ));
+    }
+    return Ok(value.to_string());
+}
+
+// Columns that can be used for the queries filter (see --filter-columns)
+pub const FILTER_COLUMNS: [&str; 8] = [
+    "client_hostname",
+    "initial_address",
+    "os_user",
+    "user",
+    "initial_user",
+    "client_name",
+    "query_id",
+    "query",
+];
+
+fn parse_filter_column(value: &str) -> Result<String, String> {
+    if !FILTER_COLUMNS.contains(&value) {
+        return Err(format!(
+            "Unknown filter column {} (valid columns: {})",
+            value,
+            FILTER_COLUMNS.join(",")
+        ));
+    }
+    return Ok(value.to_string());
+}
+
+// metric=warning:critical,...
+fn parse_summary_thresholds(value: &str) -> Result<HashMap<String, (f64, f64)>, String> {
+    const METRICS: [&str; 10] = [
+        "memory",
+        "cpu",
+        "queries",
+        "merges",
+        "mutations",
+        "replication_queue",
+        "replication_queue_tries",
+        "fetches",
+        "buffers",
+        "distributed_inserts",
+    ];
+    let mut thresholds = HashMap::new();
+    for threshold in value.split(',') {
+        let (metric, ratios) = threshold.split_once('=').ok_or(format!(
+            "Invalid threshold {} (expected metric=warning:critical)",
+            threshold
+        ))?;
+        if !METRICS.contains(&metric) {
+            return Err(format!("Unknown metric {} ({})", metric, METRICS.join("/")));
+        }
+        let (warning, critical) = ratios.split_once(':').ok_or(format!(
+            "Invalid thresholds {} (expected warning:critical)",
+            ratios
+        ))?;
+        let warning = warning.parse::<f64>().map_err(|e| e.to_string())?;
+        let critical = critical.parse::<f64>().map_err(|e| e.to_string())?;
+        thresholds.insert(metric.to_string(), (warning, critical));
+    }
+    return Ok(thresholds);
+}
+
+#[derive(Clone, Debug)]
+pub struct SortOption {
+    pub column: String,
+    pub ascending: bool,
+}
+
+fn parse_sort(value: &str) -> Result<SortOption, String> {
+    let (column, direction) = value.split_once(':').unwrap_or((value, "desc"));
+    if column.is_empty() {
+        return Err("Empty column".to_string());
+    }
+    let ascending = match direction {
+        "asc" => true,
+        "desc" => false,
+        _ => return Err(format!("Invalid direction {} (asc/desc)", direction)),
+    };
+    return Ok(SortOption {
+        column: column.to_string(),
+        ascending,
+    });
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DurationFormat {
+    /// 83.00
+    Seconds,
+    /// 1m23s
+    Human,
+}
+impl DurationFormat {
+    pub fn format(&self, seconds: f64) -> String {
+        match self {
+            DurationFormat::Seconds => return format!("{:.2}", seconds),
+            DurationFormat::Human => {
+                // Sub-second precision is not interesting for long durations
+                let duration = if seconds < 1. {
+                    time::Duration::from_millis((seconds * 1e3) as u64)
+                } else {
+                    time::Duration::from_secs(seconds.round() as u64)
+                };
+                return humantime::format_duration(duration)
+                    .to_string()
+                    .replace(' ', "");
+            }
+        }
+    }
+}
+
 #[derive(Args, Clone)]
 pub struct ViewOptions {
     #[arg(
@@ -171,10 +448,67 @@ pub struct ViewOptions {
     #[arg(long, short('e'), value_parser = parse_datetime_or_date, default_value_t = Local::now())]
     /// End of the time interval
     pub end: DateTime<Local>,
+    // The end of the interval is "now" (it was not passed explicitly and was not changed since,
+    // see save_time_frame())
+    #[arg(skip)]
+    pub end_is_now: bool,
+    /// File to persist the time interval across restarts (restored unless --start/--end are
+    /// passed), default: $XDG_STATE_HOME/chdig/time_frame (or ~/.local/state/chdig/time_frame)
+    #[arg(long)]
+    pub time_frame_file: Option<String>,
 
     /// Wrap long lines (more CPU greedy)
     #[arg(long, default_value_t = false)]
     pub wrap: bool,
+    /// Show full query_id (by default only the prefix is visible)
+    #[arg(long, default_value_t = false)]
+    pub full_query_id: bool,
+    /// Do not show errors of actions in dialogs, but write them to the log (and status bar)
+    /// instead (errors of the server communication are still shown in dialogs)
+    #[arg(long, default_value_t = false)]
+    pub quiet_errors: bool,
+    /// Initial sort of the views (column:asc or column:desc, i.e. mem:desc), column is the name
+    /// from the header of the view (ignored for views that does not have such column)
+    #[arg(long, value_parser = parse_sort)]
+    pub sort: Option<SortOption>,
+    /// Thresholds for coloring of the summary metrics, as ratio of the limit (i.e. for memory -
+    /// OS total), metric=warning:critical,... (i.e. memory=0.7:0.9,replication_queue=0:0.5), the
+    /// default is 0.5:0.9 (yellow/red)
+    #[arg(long, value_parser = parse_summary_thresholds)]
+    pub summary_thresholds: Option<HashMap<String, (f64, f64)>>,
+    /// Number of refreshes to show in sparklines for memory, CPU and queries in the summary (0 -
+    /// disable)
+    #[arg(long, default_value_t = 20)]
+    pub summary_history: usize,
+    /// Timezone for the clock and the time of the last update in the status bar (i.e.
+    /// Europe/Amsterdam, default - local timezone)
+    #[arg(long, value_parser = |arg: &str| arg.parse::<Tz>().map_err(|e| e.to_string()))]
+    pub timezone: Option<Tz>,
+    /// Widths of the columns of the queries views, column=width,... by the name from the header
+    /// (i.e. query_id=36,user=16,query=80, by default query takes the rest of the space)
+    #[arg(long, value_parser = parse_column_widths)]
+    pub column_widths: Option<HashMap<String, usize>>,
+    /// Columns of the queries views (i.e. cpu,mem,io,read_rows,query), by default all except
+    /// optional (--cache-hit-ratio, --read-columns), host and Q# are controlled separately
+    #[arg(long, value_delimiter = ',', value_parser = parse_column)]
+    pub columns: Option<Vec<String>>,
+    /// Show filesystem cache hit ratio column in queries views
+    #[arg(long, default_value_t = false)]
+    pub cache_hit_ratio: bool,
+    /// Show read rows/bytes columns in queries views (i.e. to spot full scans)
+    #[arg(long, default_value_t = false)]
+    pub read_columns: bool,
+    /// Format of durations (i.e. elapsed)
+    #[arg(long, value_enum, default_value_t = DurationFormat::Seconds)]
+    pub duration_format: DurationFormat,
+
+    /// Automatically KILL queries that exceed any of the thresholds (i.e.
+    /// memory=50GiB,elapsed=600s), every KILL is logged (requires --i-know-what-im-doing)
+    #[arg(long, value_parser = parse_auto_kill_rule, requires = "i_know_what_im_doing")]
+    pub auto_kill_over: Option<AutoKillRule>,
+    /// Confirm usage of dangerous options (i.e. --auto-kill-over)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub i_know_what_im_doing: bool,
     // TODO: --mouse/--no-mouse (see EXIT_MOUSE_SEQUENCE in termion)
 }
 
@@ -252,14 +586,25 @@ fn read_clickhouse_client_config() -> Option<ClickHouseClientConfig> {
     return None;
 }
 
-fn parse_url(url_str: &str) -> url::Url {
+fn parse_url(url_str: &str) -> Result<url::Url> {
     // url::Url::scheme() does not works as we want,
     // since for "foo:bar@127.1" the scheme will be "foo",
-    if url_str.contains("://") {
-        return url::Url::parse(url_str).unwrap();
+    let url = if url_str.contains("://") {
+        url::Url::parse(url_str)?
+    } else {
+        url::Url::parse(&format!("tcp://{}", url_str))?
+    };
+
+    // NOTE: only native protocol is supported (see TODO.md), so give a proper error instead of
+    // obscure one from the driver
+    if url.scheme() == "http" || url.scheme() == "https" {
+        return Err(anyhow!(
+            "HTTP interface is not supported, use native protocol instead (tcp://{}:9000, or 9440 with ?secure=true)",
+            url.host_str().unwrap_or("localhost")
+        ));
     }
 
-    return url::Url::parse(&format!("tcp://{}", url_str)).unwrap();
+    return Ok(url);
 }
 
 fn is_local_address(host: &str) -> bool {
@@ -279,9 +624,15 @@ fn is_local_address(host: &str) -> bool {
     return false;
 }
 
-fn clickhouse_url_defaults(options: &mut ChDigOptions) {
-    let mut url = parse_url(&options.clickhouse.url.clone().unwrap_or_default());
-    let config: Option<ClickHouseClientConfig> = read_clickhouse_client_config();
+fn clickhouse_url_defaults(options: &mut ChDigOptions) -> Result<()> {
+    return clickhouse_url_defaults_with_config(options, read_clickhouse_client_config());
+}
+
+fn clickhouse_url_defaults_with_config(
+    options: &mut ChDigOptions,
+    config: Option<ClickHouseClientConfig>,
+) -> Result<()> {
+    let mut url = parse_url(&options.clickhouse.url.clone().unwrap_or_default())?;
     let connection = &options.clickhouse.connection;
     let mut has_secure: Option<bool> = None;
     let mut has_skip_verify: Option<bool> = None;
@@ -296,6 +647,22 @@ fn clickhouse_url_defaults(options: &mut ChDigOptions) {
         }
     }
 
+    // --host, the first host is used for the URL, others are passed as alt_hosts
+    let hosts = options
+        .clickhouse
+        .host
+        .clone()
+        .unwrap_or_default()
+        .iter()
+        .map(|host| parse_url(&format!("tcp://{}", host.trim())))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(host) = hosts.first() {
+        url.set_host(host.host_str()).unwrap();
+        if host.port().is_some() {
+            url.set_port(host.port()).unwrap();
+        }
+    }
+
     // host should be set first, since url crate does not allow to set user/password without host.
     let has_host = url.host().is_some();
     if !has_host {
@@ -351,7 +718,9 @@ fn clickhouse_url_defaults(options: &mut ChDigOptions) {
                     continue;
                 }
                 if connection_found {
-                    panic!("Multiple connections had been matched. Fix you config.xml");
+                    return Err(anyhow!(
+                        "Multiple connections had been matched. Fix you config.xml"
+                    ));
                 }
 
                 connection_found = true;
@@ -388,22 +757,65 @@ fn clickhouse_url_defaults(options: &mut ChDigOptions) {
             }
 
             if !connection_found {
-                panic!("Connection {} was not found", connection);
+                return Err(anyhow!("Connection {} was not found", connection));
             }
         }
     } else if connection.is_some() {
-        panic!("No client config had been read, while --connection was set");
+        return Err(anyhow!(
+            "No client config had been read, while --connection was set"
+        ));
     }
 
     // - 9000 for non secure
     // - 9440 for secure
+    let default_port = if has_secure.unwrap_or_default() {
+        9440
+    } else {
+        9000
+    };
     if url.port().is_none() {
-        url.set_port(Some(if has_secure.unwrap_or_default() {
-            9440
-        } else {
-            9000
-        }))
-        .unwrap();
+        url.set_port(Some(default_port)).unwrap();
+    }
+    // Other hosts from --host, the driver fails over to them in order
+    let alt_hosts = hosts
+        .iter()
+        .skip(1)
+        .map(|host| {
+            format!(
+                "{}:{}",
+                host.host_str().unwrap(),
+                host.port().unwrap_or(default_port)
+            )
+        })
+        .collect::<Vec<_>>();
+    if !alt_hosts.is_empty() {
+        let pairs = url
+            .query_pairs()
+            .into_owned()
+            .filter(|(key, _)| key != "alt_hosts")
+            .collect::<Vec<_>>();
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs)
+            .append_pair("alt_hosts", &alt_hosts.join(","));
+    }
+
+    // --connect-timeout/--query-timeout (takes precedence over the URL)
+    for (name, timeout) in [
+        ("connection_timeout", options.clickhouse.connect_timeout),
+        ("query_timeout", options.clickhouse.query_timeout),
+    ] {
+        if let Some(timeout) = timeout {
+            let pairs = url
+                .query_pairs()
+                .into_owned()
+                .filter(|(key, _)| key != name)
+                .collect::<Vec<_>>();
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(pairs)
+                .append_pair(name, &format!("{}ms", timeout.as_millis()));
+        }
     }
 
     let mut url_safe = url.clone();
@@ -417,10 +829,23 @@ fn clickhouse_url_defaults(options: &mut ChDigOptions) {
     // some default settings in URL
     {
         let pairs: HashMap<_, _> = url_safe.query_pairs().into_owned().collect();
-        let is_local = is_local_address(&url.host().unwrap().to_string());
+        // Enable compression in non-local network (in the same way as clickhouse does by default),
+        // unless it is overridden explicitly (then there is no need to resolve the host)
+        let compression = if pairs.contains_key("compression") || options.clickhouse.no_compression
+        {
+            false
+        } else if options.clickhouse.force_compression {
+            true
+        } else {
+            // Any of the hosts is not local (see --host)
+            !is_local_address(&url.host().unwrap().to_string())
+                || hosts
+                    .iter()
+                    .skip(1)
+                    .any(|host| !is_local_address(host.host_str().unwrap()))
+        };
         let mut mut_pairs = url.query_pairs_mut();
-        // Enable compression in non-local network (in the same way as clickhouse does by default)
-        if !pairs.contains_key("compression") && !is_local {
+        if compression {
             mut_pairs.append_pair("compression", "lz4");
         }
         // default is: 500ms (too small)
@@ -442,15 +867,207 @@ fn clickhouse_url_defaults(options: &mut ChDigOptions) {
     }
 
     options.clickhouse.url = Some(url.to_string());
+    return Ok(());
+}
+
+/// Names of the connections from connections_credentials of the clickhouse-client config
+pub fn get_connections() -> Vec<String> {
+    return read_clickhouse_client_config()
+        .map(|config| {
+            config
+                .connections_credentials
+                .into_iter()
+                .map(|c| c.name)
+                .collect()
+        })
+        .unwrap_or_default();
+}
+
+/// Options for another connection from connections_credentials (for switching at runtime)
+pub fn get_connection_options(options: &ChDigOptions, connection: &str) -> Result<ChDigOptions> {
+    let mut options = options.clone();
+    // Endpoint/credentials should be taken from the connection, not from the current URL (or
+    // --host/--ssh-jump, that belongs to the current connection)
+    options.clickhouse.url = None;
+    options.clickhouse.host = None;
+    options.clickhouse.ssh_jump = None;
+    options.clickhouse.connection = Some(connection.to_string());
+    clickhouse_url_defaults(&mut options)?;
+    return Ok(options);
+}
+
+fn time_frame_file(options: &ViewOptions) -> Option<path::PathBuf> {
+    if let Some(file) = &options.time_frame_file {
+        return Some(path::PathBuf::from(file));
+    }
+    return Some(state_dir()?.join("time_frame"));
+}
+
+// $XDG_STATE_HOME/chdig (or ~/.local/state/chdig)
+fn state_dir() -> Option<path::PathBuf> {
+    let state_home = env::var("XDG_STATE_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(path::PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .ok()
+                .map(|home| path::Path::new(&home).join(".local/state"))
+        })?;
+    return Some(state_home.join("chdig"));
+}
+
+fn summary_baselines_dir() -> Result<path::PathBuf> {
+    return Ok(state_dir()
+        .ok_or_else(|| anyhow!("Cannot determine state directory"))?
+        .join("baselines"));
+}
+
+fn summary_baseline_file(name: &str) -> Result<path::PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(anyhow!("Invalid baseline name '{}'", name));
+    }
+    return Ok(summary_baselines_dir()?.join(format!("{}.json", name)));
+}
+
+/// Names of the saved summary baselines (sorted)
+pub fn get_summary_baselines() -> Vec<String> {
+    let Ok(dir) = summary_baselines_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+        .collect::<Vec<_>>();
+    names.sort();
+    return names;
+}
+
+/// Save summary metrics (see ClickHouseServerSummary::metrics()) as a named baseline
+pub fn save_summary_baseline(name: &str, metrics: &[(&str, u64)]) -> Result<()> {
+    let file = summary_baseline_file(name)?;
+    let metrics = metrics
+        .iter()
+        .map(|(metric, value)| (metric.to_string(), *value))
+        .collect::<BTreeMap<String, u64>>();
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&file, serde_json::to_string_pretty(&metrics)?)?;
+    return Ok(());
+}
+
+pub fn read_summary_baseline(name: &str) -> Result<BTreeMap<String, u64>> {
+    let file = summary_baseline_file(name)?;
+    let content =
+        fs::read_to_string(&file).map_err(|e| anyhow!("Cannot read {}: {}", file.display(), e))?;
+    return Ok(serde_json::from_str(&content)?);
+}
+
+// "now" or "now-<seconds>" (relative to now), or absolute datetime
+fn parse_time_frame_point(value: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    if let Some(offset) = value.strip_prefix("now") {
+        if offset.is_empty() {
+            return Some(now);
+        }
+        let seconds = offset.strip_prefix('-')?.parse::<i64>().ok()?;
+        return Some(now - Duration::try_seconds(seconds)?);
+    }
+    return parse_datetime_or_date(value).ok();
+}
+
+// (start, end, is end "now")
+type TimeFrame = (DateTime<Local>, DateTime<Local>, bool);
+
+fn parse_time_frame(content: &str, now: DateTime<Local>) -> Option<TimeFrame> {
+    let mut lines = content.lines();
+    let start_line = lines.next()?;
+    let end_line = lines.next()?;
+    let start = parse_time_frame_point(start_line, now)?;
+    let end = parse_time_frame_point(end_line, now)?;
+    if start > end {
+        return None;
+    }
+    return Some((start, end, end_line == "now"));
+}
+
+// The interval is saved relative to now, so that after restart it points to the same offsets
+// (i.e. "last hour"), and the end is kept as "now" if it was "now"
+fn format_time_frame(options: &ViewOptions, now: DateTime<Local>) -> String {
+    let offset = |time: DateTime<Local>| (now - time).num_seconds().max(0);
+    let end = if options.end_is_now {
+        "now".to_string()
+    } else {
+        format!("now-{}", offset(options.end))
+    };
+    return format!("now-{}\n{}\n", offset(options.start), end);
+}
+
+// Missing or corrupted file is ignored
+fn read_time_frame(options: &ViewOptions) -> Option<TimeFrame> {
+    let content = fs::read_to_string(time_frame_file(options)?).ok()?;
+    return parse_time_frame(&content, Local::now());
+}
+
+// Restore the time interval from the previous run, unless it was passed explicitly
+fn restore_time_frame(options: &mut ViewOptions, matches: &ArgMatches, saved: Option<TimeFrame>) {
+    let is_explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    options.end_is_now = !is_explicit("end");
+    if is_explicit("start") || is_explicit("end") {
+        return;
+    }
+    if let Some((start, end, end_is_now)) = saved {
+        options.start = start;
+        options.end = end;
+        options.end_is_now = end_is_now;
+    }
+}
+
+/// Persist the time interval (see --time-frame-file), errors are only logged
+pub fn save_time_frame(options: &ViewOptions) {
+    let Some(file) = time_frame_file(options) else {
+        return;
+    };
+    let content = format_time_frame(options, Local::now());
+    let result = file
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&file, content));
+    if let Err(err) = result {
+        log::warn!("Cannot save time frame to {}: {}", file.display(), err);
+    }
 }
 
 fn adjust_defaults(options: &mut ChDigOptions) {
-    clickhouse_url_defaults(options);
+    if let Err(err) = clickhouse_url_defaults(options) {
+        panic!("{}", err);
+    }
 
     // FIXME: overrides_with works before default_value_if, hence --no-group-by never works
     if options.view.no_group_by {
         options.view.group_by = false;
     }
+
+    let url = parse_url(options.clickhouse.url.as_ref().unwrap()).unwrap();
+    let readonly = url
+        .query_pairs()
+        .any(|(key, value)| key == "readonly" && value != "0");
+    if readonly {
+        options.clickhouse.read_only = true;
+    }
+
+    if options.view.auto_kill_over.is_some() && options.clickhouse.read_only {
+        panic!("--auto-kill-over cannot be used in readonly mode");
+    }
+
+    if options.clickhouse.max_connections == 0 {
+        panic!("--max-connections should be greater than 0");
+    }
 }
 
 // TODO:
@@ -461,7 +1078,8 @@ fn adjust_defaults(options: &mut ChDigOptions) {
 //     [1]: https://github.com/clap-rs/clap/discussions/2763
 //     [2]: https://github.com/bnjjj/twelf/issues/15
 pub fn parse() -> ChDigOptions {
-    let mut options = ChDigOptions::parse();
+    let matches = ChDigOptions::command().get_matches();
+    let mut options = ChDigOptions::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     // Generate autocompletion
     if let Some(shell) = options.service.completion {
@@ -473,5 +1091,202 @@ pub fn parse() -> ChDigOptions {
 
     adjust_defaults(&mut options);
 
+    let saved = read_time_frame(&options.view);
+    restore_time_frame(&mut options.view, &matches, saved);
+
     return options;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // View options as in parse() (time frame is restored from `saved`)
+    fn view_options(args: &[&str], saved: Option<TimeFrame>) -> ViewOptions {
+        let matches = ChDigOptions::command()
+            .try_get_matches_from([&["chdig"], args].concat())
+            .unwrap();
+        let mut options = ChDigOptions::from_arg_matches(&matches).unwrap();
+        restore_time_frame(&mut options.view, &matches, saved);
+        return options.view;
+    }
+
+    // Final URL (without client config)
+    fn clickhouse_url(args: &[&str]) -> url::Url {
+        let mut options = ChDigOptions::try_parse_from([&["chdig"], args].concat()).unwrap();
+        clickhouse_url_defaults_with_config(&mut options, None).unwrap();
+        return parse_url(options.clickhouse.url.as_ref().unwrap()).unwrap();
+    }
+
+    fn url_param(url: &url::Url, name: &str) -> Option<String> {
+        return url
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string());
+    }
+
+    #[test]
+    fn test_http_is_not_supported() {
+        for url in ["http://127.1:8123", "https://127.1:8443"] {
+            let mut options = ChDigOptions::try_parse_from(["chdig", "--url", url]).unwrap();
+            assert!(clickhouse_url_defaults_with_config(&mut options, None).is_err());
+        }
+    }
+
+    #[test]
+    fn test_hosts() {
+        let url = clickhouse_url(&["--host", "127.0.0.1:9001,127.0.0.2, 127.0.0.3:9002"]);
+        assert_eq!(url.host_str(), Some("127.0.0.1"));
+        assert_eq!(url.port(), Some(9001));
+        assert_eq!(
+            url_param(&url, "alt_hosts").as_deref(),
+            Some("127.0.0.2:9000,127.0.0.3:9002")
+        );
+    }
+
+    #[test]
+    fn test_hosts_secure_default_port() {
+        let url = clickhouse_url(&[
+            "--url",
+            "tcp://127.0.0.10?secure=true",
+            "--host",
+            "127.0.0.1,127.0.0.2",
+        ]);
+        // --host overrides the host from the URL
+        assert_eq!(url.host_str(), Some("127.0.0.1"));
+        assert_eq!(url.port(), Some(9440));
+        assert_eq!(
+            url_param(&url, "alt_hosts").as_deref(),
+            Some("127.0.0.2:9440")
+        );
+    }
+
+    #[test]
+    fn test_single_host() {
+        let url = clickhouse_url(&["--host", "127.0.0.1"]);
+        assert_eq!(url.host_str(), Some("127.0.0.1"));
+        assert_eq!(url.port(), Some(9000));
+        assert_eq!(url_param(&url, "alt_hosts"), None);
+    }
+
+    fn compression(args: &[&str]) -> Vec<String> {
+        let url = clickhouse_url(args);
+        return url
+            .query_pairs()
+            .filter(|(key, _)| key == "compression")
+            .map(|(_, value)| value.to_string())
+            .collect();
+    }
+
+    #[test]
+    fn test_compression_auto() {
+        assert!(compression(&["--url", "127.0.0.1"]).is_empty());
+        assert_eq!(compression(&["--url", "127.0.0.2"]), vec!["lz4"]);
+    }
+
+    #[test]
+    fn test_compression_flags() {
+        assert_eq!(
+            compression(&["--url", "127.0.0.1", "--force-compression"]),
+            vec!["lz4"]
+        );
+        assert!(compression(&["--url", "127.0.0.2", "--no-compression"]).is_empty());
+        assert!(
+            ChDigOptions::try_parse_from(["chdig", "--force-compression", "--no-compression"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_compression_url_precedence() {
+        // compression= from the URL takes precedence over the flags
+        assert_eq!(
+            compression(&["--url", "127.0.0.1?compression=none", "--force-compression"]),
+            vec!["none"]
+        );
+        assert_eq!(
+            compression(&["--url", "127.0.0.2?compression=lz4", "--no-compression"]),
+            vec!["lz4"]
+        );
+    }
+
+    #[test]
+    fn test_timeouts_default() {
+        let url = clickhouse_url(&["--url", "127.0.0.1"]);
+        assert_eq!(url_param(&url, "connection_timeout").as_deref(), Some("5s"));
+        assert_eq!(url_param(&url, "query_timeout").as_deref(), Some("600s"));
+    }
+
+    #[test]
+    fn test_timeouts_override_url() {
+        let url = clickhouse_url(&[
+            "--url",
+            "127.0.0.1?connection_timeout=1s&query_timeout=10s",
+            "--connect-timeout",
+            "2s",
+            "--query-timeout",
+            "1m",
+        ]);
+        let params = url.query_pairs().into_owned().collect::<Vec<_>>();
+        let connection_timeout = params
+            .iter()
+            .filter(|(key, _)| key == "connection_timeout")
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>();
+        let query_timeout = params
+            .iter()
+            .filter(|(key, _)| key == "query_timeout")
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(connection_timeout, vec!["2000ms"]);
+        assert_eq!(query_timeout, vec!["60000ms"]);
+    }
+
+    #[test]
+    fn test_time_frame_end_is_now() {
+        let options = view_options(&[], None);
+        assert!(options.end_is_now);
+
+        let now = options.end + Duration::try_hours(5).unwrap();
+        let content = format_time_frame(&options, now);
+        assert_eq!(content, format!("now-{}\nnow\n", 6 * 3600));
+
+        let restart = now + Duration::try_days(1).unwrap();
+        let options = view_options(&[], parse_time_frame(&content, restart));
+        assert!(options.end_is_now);
+        assert_eq!(options.end, restart);
+        assert_eq!(options.start, restart - Duration::try_hours(6).unwrap());
+    }
+
+    #[test]
+    fn test_time_frame_explicit() {
+        let saved = parse_time_frame("now-60\nnow\n", Local::now());
+        let options = view_options(&["-e", "2024-01-02"], saved);
+        assert!(!options.end_is_now);
+        assert_eq!(options.end, parse_datetime_or_date("2024-01-02").unwrap());
+
+        let options = view_options(&["-b", "2024-01-01"], saved);
+        assert!(options.end_is_now);
+        assert_eq!(options.start, parse_datetime_or_date("2024-01-01").unwrap());
+    }
+
+    #[test]
+    fn test_time_frame_relative() {
+        let now = Local::now();
+        let saved = parse_time_frame("now-1800\nnow-600\n", now);
+        let options = view_options(&[], saved);
+        assert!(!options.end_is_now);
+        assert_eq!(options.start, now - Duration::try_minutes(30).unwrap());
+        assert_eq!(options.end, now - Duration::try_minutes(10).unwrap());
+        assert_eq!(format_time_frame(&options, now), "now-1800\nnow-600\n");
+    }
+
+    #[test]
+    fn test_time_frame_corrupted() {
+        let now = Local::now();
+        assert!(parse_time_frame("", now).is_none());
+        assert!(parse_time_frame("now-foo\nnow\n", now).is_none());
+        assert!(parse_time_frame("now\nnow-60\n", now).is_none());
+        assert!(parse_time_frame("2024-01-01T00:00:00\n2024-01-02T00:00:00\n", now).is_some());
+    }
+}
@@ -1,16 +1,38 @@
+use crate::interpreter::options::DurationFormat;
 use chrono::{DateTime, Local};
+use serde::{Serialize, Serializer};
 use std::collections::HashMap;
 
-#[derive(Clone, Debug)]
+fn serialize_datetime<S: Serializer>(value: &DateTime<Local>, s: S) -> Result<S::Ok, S::Error> {
+    return s.serialize_str(&value.to_rfc3339());
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct QueryProcess {
+    #[serde(skip)]
     pub selection: bool,
     pub host_name: String,
     pub user: String,
+    pub client_address: String,
     pub threads: usize,
+    // The first one is the master thread (the thread that executes the query)
+    pub thread_ids: Vec<u64>,
     pub memory: i64,
     pub elapsed: f64,
+    pub read_rows: u64,
+    pub read_bytes: u64,
+    // Available only for system.processes (0 otherwise)
+    pub total_rows_approx: u64,
+    // Available only for system.query_log
+    pub result_rows: u64,
+    pub result_bytes: u64,
+    #[serde(serialize_with = "serialize_datetime")]
     pub query_start_time_microseconds: DateTime<Local>,
+    #[serde(serialize_with = "serialize_datetime")]
     pub query_end_time_microseconds: DateTime<Local>,
+    // Available only for system.query_log (KILL QUERY or cancelled by the client)
+    pub killed: bool,
+    pub kill_reason: String,
     // Is the name good enough? Maybe simply "queries" or "shards_queries"?
     pub subqueries: u64,
     pub is_initial_query: bool,
@@ -24,15 +46,187 @@ pub struct QueryProcess {
     pub settings: HashMap<String, String>,
 
     // Used for metric rates (like top(1) shows)
+    #[serde(skip)]
     pub prev_elapsed: Option<f64>,
+    #[serde(skip)]
     pub prev_profile_events: Option<HashMap<String, u64>>,
 
     // If running is true, then the metrics will be shown as per-second rate, otherwise raw data.
     // Since for system.processes we indeed the rates, while for slow queries/last queries raw
     // data.
     pub running: bool,
+
+    // How to render durations (--duration-format)
+    #[serde(skip)]
+    pub duration_format: DurationFormat,
 }
 impl QueryProcess {
+    /// Ratio of result rows to read rows (in percents), i.e. how much data had been filtered.
+    pub fn selectivity(&self) -> Option<f64> {
+        if self.read_rows == 0 {
+            return None;
+        }
+        return Some(self.result_rows as f64 / self.read_rows as f64 * 100.);
+    }
+
+    /// Enabled allow_* settings (allow_experimental_*, allow_suspicious_*, ...), sorted by name.
+    pub fn experimental_settings(&self) -> Vec<(&str, &str)> {
+        let mut settings = self
+            .settings
+            .iter()
+            .filter(|(name, value)| {
+                name.starts_with("allow_") && !matches!(value.as_str(), "0" | "false")
+            })
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        settings.sort();
+        return settings;
+    }
+
+    /// Progress of the query (read_rows of total_rows_approx, in percents), None if unknown.
+    pub fn progress(&self) -> Option<f64> {
+        if self.total_rows_approx == 0 {
+            return None;
+        }
+        return Some((self.read_rows as f64 / self.total_rows_approx as f64 * 100.).min(100.));
+    }
+
+    /// Filesystem cache hit ratio (in percents) by bytes, None if nothing had been read via cache.
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        let from_cache = *self
+            .profile_events
+            .get("CachedReadBufferReadFromCacheBytes")
+            .unwrap_or(&0);
+        let from_source = *self
+            .profile_events
+            .get("CachedReadBufferReadFromSourceBytes")
+            .unwrap_or(&0);
+        let total = from_cache + from_source;
+        if total == 0 {
+            return None;
+        }
+        return Some(from_cache as f64 / total as f64 * 100.);
+    }
+
+    /// Time spent waiting for locks (table RWLocks and context lock) in milliseconds.
+    pub fn lock_wait_ms(&self) -> u64 {
+        let get_event = |name: &str| *self.profile_events.get(name).unwrap_or(&0);
+        return get_event("RWLockReadersWaitMilliseconds")
+            + get_event("RWLockWritersWaitMilliseconds")
+            + get_event("ContextLockWaitMicroseconds") / 1000;
+    }
+
+    /// Time the query waited for memory due to memory overcommit in seconds (0 - not throttled).
+    pub fn memory_overcommit_wait_sec(&self) -> f64 {
+        return *self
+            .profile_events
+            .get("MemoryOvercommitWaitTimeMicroseconds")
+            .unwrap_or(&0) as f64
+            / 1e6;
+    }
+
+    /// Remote endpoints from remote()/remoteSecure()/cluster()/clusterAllReplicas() table
+    /// functions in the query (first argument as is, i.e. 'host{1,2}:9000' or cluster name).
+    pub fn remote_endpoints(&self) -> Vec<String> {
+        // NOTE: to_ascii_lowercase() preserves offsets (unlike to_lowercase())
+        let query = self.original_query.to_ascii_lowercase();
+        let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+        let mut result = Vec::<String>::new();
+        for function in ["remote", "remotesecure", "cluster", "clusterallreplicas"] {
+            for (pos, _) in query.match_indices(function) {
+                if query[..pos].chars().last().is_some_and(is_identifier) {
+                    continue;
+                }
+                let args = query[pos + function.len()..].trim_start();
+                if !args.starts_with('(') {
+                    continue;
+                }
+                let args_pos = query.len() - args.len() + 1;
+                let args = self.original_query[args_pos..].trim_start();
+
+                let endpoint = match args.chars().next() {
+                    Some(quote @ ('\'' | '"')) => args[1..].split(quote).next().unwrap_or_default(),
+                    _ => args.split([',', ')']).next().unwrap_or_default().trim(),
+                };
+                if !endpoint.is_empty() && !result.iter().any(|e| e == endpoint) {
+                    result.push(endpoint.to_string());
+                }
+            }
+        }
+        return result;
+    }
+
+    /// Tables referenced in the query (FROM/JOIN/INTO), best effort, since system.processes does
+    /// not have "tables" column (unlike system.query_log).
+    pub fn tables(&self) -> Vec<String> {
+        // NOTE: to_ascii_lowercase() preserves offsets (unlike to_lowercase())
+        let query = self.original_query.to_ascii_lowercase();
+        let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let is_table_char = |c: char| is_identifier(c) || c == '.' || c == '`' || c == '"';
+
+        let mut result = Vec::<String>::new();
+        for keyword in ["from", "join", "into"] {
+            for (pos, _) in query.match_indices(keyword) {
+                let end = pos + keyword.len();
+                if query[..pos].chars().last().is_some_and(is_identifier)
+                    || query[end..].chars().next().is_some_and(is_identifier)
+                {
+                    continue;
+                }
+                let rest = query[end..].trim_start();
+                let rest_pos = query.len() - rest.len();
+                let len = rest.find(|c| !is_table_char(c)).unwrap_or(rest.len());
+                // Table functions (i.e. numbers(10)) and subqueries
+                if len == 0 || rest[len..].trim_start().starts_with('(') {
+                    continue;
+                }
+                let table = self.original_query[rest_pos..rest_pos + len].replace(['`', '"'], "");
+                // INSERT INTO FUNCTION, FROM SELECT (i.e. CREATE VIEW ... AS SELECT ... FROM)
+                if ["function", "select", "table"].contains(&table.to_ascii_lowercase().as_str()) {
+                    continue;
+                }
+                if !result.contains(&table) {
+                    result.push(table);
+                }
+            }
+        }
+        return result;
+    }
+
+    /// Limit (from the query settings) that is the closest to be exceeded by the query.
+    ///
+    /// Returns (setting name, current value, limit), and None if no limits had been set.
+    ///
+    /// NOTE: only changed settings are available, so server defaults are not taken into account.
+    pub fn closest_limit(&self) -> Option<(&'static str, f64, f64)> {
+        let limits = [
+            ("max_execution_time", self.elapsed),
+            ("max_memory_usage", self.memory as f64),
+            ("max_rows_to_read", self.read_rows as f64),
+        ];
+
+        let mut result: Option<(&'static str, f64, f64)> = None;
+        for (name, value) in limits {
+            let limit = self
+                .settings
+                .get(name)
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(0.);
+            // 0 means unlimited
+            if limit <= 0. {
+                continue;
+            }
+            if let Some((_, closest_value, closest_limit)) = result {
+                if value / limit <= closest_value / closest_limit {
+                    continue;
+                }
+            }
+            result = Some((name, value, limit));
+        }
+        return result;
+    }
+
     // NOTE: maybe it should be corrected with moving sampling?
     pub fn cpu(&self) -> f64 {
         if !self.running {
@@ -133,6 +327,16 @@ impl QueryProcess {
         return (ms as f64) / 1e6 / self.elapsed * 100.;
     }
 
+    /// Rows read (SELECT) per second
+    pub fn read_rows_rate(&self) -> f64 {
+        return self.get_per_second_rate_events_multi(&["SelectedRows"]);
+    }
+
+    /// Rows written (INSERT) per second
+    pub fn written_rows_rate(&self) -> f64 {
+        return self.get_per_second_rate_events_multi(&["InsertedRows"]);
+    }
+
     pub fn net_io(&self) -> f64 {
         return self.get_per_second_rate_events_multi(&[
             "NetworkSendBytes",
@@ -0,0 +1,137 @@
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long to wait for the tunnel to start accepting connections
+const SSH_TUNNEL_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Local port forwarding via jump host (see --ssh-jump), uses ssh binary, so the ssh config
+/// (keys, agent, ProxyJump, ...) is respected.
+///
+/// The ssh process is killed on drop.
+pub struct SshTunnel {
+    jump: String,
+    child: Mutex<Child>,
+    local_port: u16,
+    // Last line of ssh output (for errors), the output is drained by the thread, otherwise ssh
+    // will block once the pipe is full (i.e. on "channel open failed" warnings)
+    last_error: Arc<Mutex<String>>,
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        let child = self.child.get_mut().unwrap();
+        log::debug!("Stopping SSH tunnel via {} (pid {})", self.jump, child.id());
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+// user@host[:port] -> (user@host, port)
+fn parse_jump(jump: &str) -> Result<(String, Option<u16>)> {
+    if let Some((host, port)) = jump.rsplit_once(':') {
+        if let Ok(port) = port.parse::<u16>() {
+            return Ok((host.to_string(), Some(port)));
+        }
+        return Err(anyhow!("Invalid port in --ssh-jump {}", jump));
+    }
+    return Ok((jump.to_string(), None));
+}
+
+impl SshTunnel {
+    /// Forward 127.0.0.1:<random port> to host:port via jump (user@host[:port])
+    pub async fn new(jump: &str, host: &str, port: u16) -> Result<Self> {
+        let (jump_host, jump_port) = parse_jump(jump)?;
+
+        // NOTE: there is a window between closing the listener and ssh binding the port, but
+        // ExitOnForwardFailure will report the error in this case
+        let local_port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+
+        let mut command = Command::new("ssh");
+        command
+            .arg("-N")
+            .args(["-o", "ExitOnForwardFailure=yes"])
+            .args(["-o", "BatchMode=yes"])
+            .args(["-o", "ServerAliveInterval=10"])
+            .arg("-L")
+            .arg(format!("127.0.0.1:{}:{}:{}", local_port, host, port));
+        if let Some(jump_port) = jump_port {
+            command.arg("-p").arg(jump_port.to_string());
+        }
+        command
+            .arg(&jump_host)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        log::info!(
+            "Starting SSH tunnel 127.0.0.1:{} -> {}:{} via {}",
+            local_port,
+            host,
+            port,
+            jump
+        );
+        let mut child = command
+            .spawn()
+            .map_err(|e| anyhow!("Cannot start ssh for --ssh-jump {}: {}", jump, e))?;
+
+        let last_error = Arc::new(Mutex::new(String::new()));
+        if let Some(stderr) = child.stderr.take() {
+            let last_error = last_error.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(stderr)
+                    .lines()
+                    .map_while(std::result::Result::ok)
+                {
+                    log::warn!("ssh: {}", line);
+                    *last_error.lock().unwrap() = line;
+                }
+            });
+        }
+
+        let tunnel = SshTunnel {
+            jump: jump.to_string(),
+            child: Mutex::new(child),
+            local_port,
+            last_error,
+        };
+
+        let started = Instant::now();
+        loop {
+            tunnel.check()?;
+            if TcpStream::connect(("127.0.0.1", local_port)).is_ok() {
+                break;
+            }
+            if started.elapsed() > SSH_TUNNEL_TIMEOUT {
+                return Err(anyhow!(
+                    "SSH tunnel via {} did not start in {:?}",
+                    jump,
+                    SSH_TUNNEL_TIMEOUT
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        return Ok(tunnel);
+    }
+
+    pub fn local_port(&self) -> u16 {
+        return self.local_port;
+    }
+
+    /// Returns an error (with the last line of the ssh output) if the ssh process exited
+    pub fn check(&self) -> Result<()> {
+        if let Some(status) = self.child.lock().unwrap().try_wait()? {
+            return Err(anyhow!(
+                "SSH tunnel via {} exited ({}): {}",
+                self.jump,
+                status,
+                self.last_error.lock().unwrap()
+            ));
+        }
+        return Ok(());
+    }
+}
@@ -1,20 +1,31 @@
 use crate::{
     common::Stopwatch,
-    interpreter::clickhouse::{Columns, TraceType},
-    interpreter::{flamegraph, ContextArc},
+    interpreter::clickhouse::{
+        is_connection_error, Columns, TableCommand, TextLogFilter, TraceType,
+    },
+    interpreter::{
+        context::reconnect_status_callback, flamegraph, options::get_connection_options,
+        ClickHouse, ContextArc,
+    },
     view::{self, Navigation},
 };
 use anyhow::{anyhow, Result};
-use chdig::{highlight_sql, open_graph_in_browser};
+use chdig::{
+    copy_to_clipboard, explain_to_mermaid, highlight_sql, open_graph_in_browser,
+    parse_exception_chain, save_text, save_tsv,
+};
 use chrono::{DateTime, Local};
 // FIXME: "leaky abstractions"
+use cursive::theme::BaseColor;
 use cursive::traits::*;
+use cursive::utils::markup::StyledString;
 use cursive::views;
 use futures::channel::mpsc;
-use std::collections::HashMap;
+use size::{Base, SizeFormatter, Style};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub enum Event {
@@ -24,12 +35,13 @@ pub enum Event {
     UpdateSlowQueryLog(String, DateTime<Local>, DateTime<Local>, u64),
     // [filter, start, end, limit]
     UpdateLastQueryLog(String, DateTime<Local>, DateTime<Local>, u64),
-    // (view_name, [query_ids], start, end)
+    // (view_name, [query_ids], start, end, filter)
     GetQueryTextLog(
         &'static str,
         Option<Vec<String>>,
         DateTime<Local>,
         Option<DateTime<Local>>,
+        TextLogFilter,
     ),
     // [bool (true - show in TUI, false - open in browser), type, start, end]
     ShowServerFlameGraph(bool, TraceType, DateTime<Local>, DateTime<Local>),
@@ -43,9 +55,51 @@ pub enum Event {
     ),
     // [bool (true - show in TUI, false - open in browser), query_ids]
     ShowLiveQueryFlameGraph(bool, Vec<String>),
+    // (type, start time, end time, [query_ids], path)
+    SaveQueryFlameGraph(
+        TraceType,
+        DateTime<Local>,
+        Option<DateTime<Local>>,
+        Vec<String>,
+        String,
+    ),
+    // (start time, end time, [query_ids])
+    ExportQueryTraceLog(DateTime<Local>, Option<DateTime<Local>>, Vec<String>),
+    // (start time, end time, [query_ids])
+    ShowQueryAllocations(DateTime<Local>, Option<DateTime<Local>>, Vec<String>),
+    // (query, start, end)
+    ShowQueryDurations(String, DateTime<Local>, DateTime<Local>),
+    // (start time, end time, [query_ids])
+    ShowQuerySlowestProcessors(DateTime<Local>, Option<DateTime<Local>>, Vec<String>),
+    // (query_id, start, end)
+    ShowQueryException(String, DateTime<Local>, Option<DateTime<Local>>),
+    // (query_id, query, start, end)
+    ShowQueryTemporaryTables(String, String, DateTime<Local>, Option<DateTime<Local>>),
+    // (start time, end time, [query_ids])
+    ShowQueryThreadsTimeline(DateTime<Local>, Option<DateTime<Local>>, Vec<String>),
     UpdateSummary,
+    ShowThreadsBreakdown,
+    // (user, query settings)
+    ShowSettingsProfile(String, HashMap<String, String>),
     // query_id
     KillQuery(String),
+    // (start, end)
+    ShowMergesTimeline(DateTime<Local>, DateTime<Local>),
+    CheckPermissions,
+    // (database, table)
+    ShowTableFilesystemCache(String, String),
+    // (database, table)
+    ShowTableReplicas(String, String),
+    // (connection name from connections_credentials)
+    SwitchConnection(String),
+    // (dictionary name, None - all dictionaries)
+    ReloadDictionary(Option<String>),
+    // (command, database, table)
+    ExecuteTableCommand(TableCommand, String, String),
+    // (database, table, part, attach - true for ATTACH PART, false for DROP DETACHED PART)
+    AlterDetachedPart(String, String, String, bool),
+    // query_id (KILL by --auto-kill-over, result is only logged)
+    AutoKillQuery(String),
     // (database, query)
     ExecuteQuery(String, String),
     // (database, query)
@@ -58,9 +112,32 @@ pub enum Event {
     ExplainPipelineOpenGraphInBrowser(String, String),
     // (database, query)
     ExplainPlanIndexes(String, String),
+    // (database, query, is pipeline (otherwise plan))
+    ExplainMermaid(String, String, bool),
+    // (host, database, table, result part name)
+    ShowMergeImpact(Option<String>, String, String, String),
+    // (database, query, actual selected parts, actual selected granules)
+    IndexesEstimation(String, String, u64, u64),
     // TODO: support different types somehow
     // (view_name, query)
     ViewQuery(&'static str, String),
+    // (query, sort_by)
+    CustomQueryView(String, String),
+}
+
+impl Event {
+    // Events that are sent periodically by the views (so they will be retried on next update)
+    fn is_periodic(&self) -> bool {
+        return matches!(
+            self,
+            Event::UpdateProcessList(..)
+                | Event::UpdateSlowQueryLog(..)
+                | Event::UpdateLastQueryLog(..)
+                | Event::GetQueryTextLog(..)
+                | Event::UpdateSummary
+                | Event::ViewQuery(..)
+        );
+    }
 }
 
 type ReceiverArc = Arc<Mutex<mpsc::Receiver<Event>>>;
@@ -136,6 +213,13 @@ impl Worker {
 #[tokio::main(flavor = "current_thread")]
 async fn start_tokio(context: ContextArc, receiver: ReceiverArc) {
     let mut slow_processing = false;
+    let mut last_event_time = Instant::now();
+    let keepalive_interval = context
+        .lock()
+        .unwrap()
+        .options
+        .clickhouse
+        .keepalive_interval;
 
     log::info!("Event worker started");
 
@@ -144,6 +228,11 @@ async fn start_tokio(context: ContextArc, receiver: ReceiverArc) {
 
         // No message available.
         if result.is_err() {
+            if !keepalive_interval.is_zero() && last_event_time.elapsed() > keepalive_interval {
+                keepalive(context.clone()).await;
+                last_event_time = Instant::now();
+            }
+
             // Same as INPUT_POLL_DELAY_MS, but I hate such implementations, both should be fixed.
             thread::sleep(Duration::from_millis(30));
 
@@ -158,6 +247,7 @@ async fn start_tokio(context: ContextArc, receiver: ReceiverArc) {
 
         let event = event_result.unwrap();
         log::trace!("Got event: {:?}", event);
+        last_event_time = Instant::now();
 
         let mut need_clear = false;
         let cb_sink = context.lock().unwrap().cb_sink.clone();
@@ -180,19 +270,32 @@ async fn start_tokio(context: ContextArc, receiver: ReceiverArc) {
         update_status(&status);
 
         let stopwatch = Stopwatch::start_new();
-        if let Err(err) = process_event(context.clone(), event.clone(), &mut need_clear).await {
-            cb_sink
-                .send(Box::new(move |siv: &mut cursive::Cursive| {
-                    siv.add_layer(views::Dialog::info(err.to_string()));
-                }))
-                // Ignore errors on exit
-                .unwrap_or_default();
-        }
-        update_status(&format!(
-            "Processing {:?} took {} ms.",
-            event,
-            stopwatch.elapsed_ms(),
-        ));
+        let result = process_event(context.clone(), event.clone(), &mut need_clear).await;
+        let status = match (result, &event) {
+            // Do not flood the UI with dialogs while the server is unavailable, the views will
+            // retry on the next update (but errors of user actions should not be hidden)
+            (Err(err), event) if event.is_periodic() && is_connection_error(&err) => {
+                log::error!("Cannot process {:?}: {}", event, err);
+                format!("Connection lost: {} (will retry on next update)", err)
+            }
+            // Keep the current connection, and do not interrupt with the dialog
+            (Err(err), Event::SwitchConnection(connection)) => {
+                log::error!("Cannot switch to connection {}: {}", connection, err);
+                format!("Cannot switch to connection {}: {}", connection, err)
+            }
+            (result, event) => {
+                if let Err(err) = result {
+                    cb_sink
+                        .send(Box::new(move |siv: &mut cursive::Cursive| {
+                            siv.add_layer(views::Dialog::info(err.to_string()));
+                        }))
+                        // Ignore errors on exit
+                        .unwrap_or_default();
+                }
+                format!("Processing {:?} took {} ms.", event, stopwatch.elapsed_ms())
+            }
+        };
+        update_status(&status);
 
         // It should not be reseted, since delay_interval should be set to the maximum service
         // query duration time.
@@ -214,6 +317,69 @@ async fn start_tokio(context: ContextArc, receiver: ReceiverArc) {
     log::info!("Event worker finished");
 }
 
+async fn keepalive(context: ContextArc) {
+    let (clickhouse, cb_sink) = {
+        let context = context.lock().unwrap();
+        (context.clickhouse.clone(), context.cb_sink.clone())
+    };
+    log::trace!("Sending keepalive");
+    if let Err(err) = clickhouse.ping().await {
+        log::error!("Keepalive failed: {}", err);
+        let message = format!("Connection lost: {}", err);
+        cb_sink
+            .send(Box::new(move |siv: &mut cursive::Cursive| {
+                siv.set_statusbar_content(message);
+            }))
+            // Ignore errors on exit
+            .unwrap_or_default();
+    }
+}
+
+// Is it SELECT (or WITH ... SELECT) query (i.e. it does not modify anything)
+fn is_select_query(query: &str) -> bool {
+    let first_word = query
+        .trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    return first_word.eq_ignore_ascii_case("SELECT") || first_word.eq_ignore_ascii_case("WITH");
+}
+
+// FORMAT/SETTINGS clause of the query (it is not possible to wrap such query into a subquery)
+fn get_format_or_settings_clause(query: &str) -> Option<String> {
+    return query
+        .split_whitespace()
+        .find(|word| word.eq_ignore_ascii_case("FORMAT") || word.eq_ignore_ascii_case("SETTINGS"))
+        .map(|word| word.to_ascii_uppercase());
+}
+
+// Names from CREATE TEMPORARY TABLE [IF NOT EXISTS] <name> in the query
+fn get_created_temporary_tables(query: &str) -> Vec<String> {
+    let words = query.split_whitespace().collect::<Vec<&str>>();
+    let mut tables = Vec::new();
+    for i in 0..words.len().saturating_sub(2) {
+        if !words[i].eq_ignore_ascii_case("TEMPORARY")
+            || !words[i + 1].eq_ignore_ascii_case("TABLE")
+        {
+            continue;
+        }
+        let mut name = i + 2;
+        if words.len() > name + 3
+            && words[name].eq_ignore_ascii_case("IF")
+            && words[name + 1].eq_ignore_ascii_case("NOT")
+            && words[name + 2].eq_ignore_ascii_case("EXISTS")
+        {
+            name += 3;
+        }
+        // Strip columns definition (i.e. "t(key Int)")
+        let table = words[name].split('(').next().unwrap_or_default();
+        if !table.is_empty() {
+            tables.push(table.to_string());
+        }
+    }
+    return tables;
+}
+
 async fn render_flamegraph(tui: bool, cb_sink: cursive::CbSink, block: Columns) -> Result<()> {
     if tui {
         cb_sink
@@ -280,9 +446,15 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
                 }))
                 .map_err(|_| anyhow!("Cannot send message to UI"))?;
         }
-        Event::GetQueryTextLog(view_name, query_ids, start_microseconds, end_microseconds) => {
+        Event::GetQueryTextLog(
+            view_name,
+            query_ids,
+            start_microseconds,
+            end_microseconds,
+            filter,
+        ) => {
             let block = clickhouse
-                .get_query_logs(&query_ids, start_microseconds, end_microseconds)
+                .get_query_logs(&query_ids, start_microseconds, end_microseconds, &filter)
                 .await?;
             cb_sink
                 .send(Box::new(move |siv: &mut cursive::Cursive| {
@@ -309,30 +481,601 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
             render_flamegraph(tui, cb_sink, flamegraph_block).await?;
             *need_clear = true;
         }
+        Event::SaveQueryFlameGraph(trace_type, start, end, query_ids, path) => {
+            let title = format!("{:?} flamegraph", trace_type);
+            let flamegraph_block = clickhouse
+                .get_flamegraph(trace_type, Some(&query_ids), Some(start), end)
+                .await?;
+            flamegraph::save_svg(flamegraph_block, &title, &path)?;
+            log::info!("{} saved to {}", title, path);
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::info(format!("{} saved to {}", title, path)));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
         Event::ShowLiveQueryFlameGraph(tui, query_ids) => {
             let flamegraph_block = clickhouse.get_live_query_flamegraph(&query_ids).await?;
             render_flamegraph(tui, cb_sink, flamegraph_block).await?;
             *need_clear = true;
         }
-        Event::ExplainPlanIndexes(database, query) => {
-            let plan = clickhouse
-                .explain_plan_indexes(database.as_str(), query.as_str())
-                .await?
-                .join("\n");
+        Event::ExportQueryTraceLog(start, end, query_ids) => {
+            let block = clickhouse
+                .get_query_raw_trace_log(&query_ids, start, end)
+                .await?;
+            if block.row_count() == 0 {
+                return Err(anyhow!("No rows in system.trace_log for the query"));
+            }
+
+            let header = block
+                .columns()
+                .iter()
+                .map(|c| c.name().to_string())
+                .collect::<Vec<_>>();
+            let mut rows = Vec::with_capacity(block.row_count());
+            for i in 0..block.row_count() {
+                let mut row = Vec::with_capacity(header.len());
+                for column in &header {
+                    row.push(block.get::<String, _>(i, column.as_str())?);
+                }
+                rows.push(row);
+            }
+            let path = save_tsv("chdig-trace-log-", &header, &rows)?;
+            let message = format!(
+                "{} trace_log rows had been exported to {}",
+                rows.len(),
+                path
+            );
+            log::info!("{}", message);
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::info(message));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowQueryAllocations(start, end, query_ids) => {
+            let block = clickhouse
+                .get_query_allocations(&query_ids, start, end)
+                .await?;
+            if block.row_count() == 0 {
+                return Err(anyhow!(
+                    "No sampled allocations in system.trace_log for the query (memory_profiler_sample_probability should be enabled)"
+                ));
+            }
+
+            // Power of two size classes (upper bound) -> (count, bytes)
+            let mut classes = BTreeMap::<u64, (u64, u64)>::new();
+            for i in 0..block.row_count() {
+                let size = block.get::<u64, _>(i, "allocation_size")?;
+                let count = block.get::<u64, _>(i, "count")?;
+                let class = classes.entry(size.next_power_of_two()).or_default();
+                class.0 += count;
+                class.1 += size * count;
+            }
+
+            let fmt_bytes = SizeFormatter::new()
+                .with_base(Base::Base2)
+                .with_style(Style::Abbreviated);
+            let max_count = classes.values().map(|(count, _)| *count).max().unwrap();
+            let mut lines = vec![format!("{:>10}  {:>10}  {:>10}", "size", "count", "bytes")];
+            for (class, (count, bytes)) in classes {
+                lines.push(format!(
+                    "{:>10}  {:>10}  {:>10}  {}",
+                    format!("<= {}", fmt_bytes.format(class as i64)),
+                    count,
+                    fmt_bytes.format(bytes as i64),
+                    "█".repeat((count * 50 / max_count).max(1) as usize),
+                ));
+            }
+            let text = lines.join("\n");
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("Allocations by size class").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(views::TextView::new(text)),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowQueryDurations(query, start, end) => {
+            let block = clickhouse.get_query_durations(&query, start, end).await?;
+            let count = block.get::<u64, _>(0, "count")?;
+            if count == 0 {
+                return Err(anyhow!(
+                    "No finished queries like this in system.query_log for the selected interval"
+                ));
+            }
+            let min = block.get::<u64, _>(0, "min_ms")?;
+            let max = block.get::<u64, _>(0, "max_ms")?;
+            let quantiles = block.get::<Vec<f64>, _>(0, "quantiles_ms")?;
+            let durations = block.get::<Vec<u64>, _>(0, "durations_ms")?;
+
+            const BUCKETS: u64 = 10;
+            const BAR_WIDTH: usize = 40;
+            let bucket_size = ((max - min) / BUCKETS).max(1);
+            let mut histogram = [0_usize; BUCKETS as usize];
+            for duration in &durations {
+                let bucket = ((duration - min) / bucket_size).min(BUCKETS - 1);
+                histogram[bucket as usize] += 1;
+            }
+            let max_bucket = *histogram.iter().max().unwrap();
+
+            let mut lines = vec![
+                format!(
+                    "Executions: {} (from {} to {})",
+                    count,
+                    start.format("%Y-%m-%d %H:%M:%S"),
+                    end.format("%Y-%m-%d %H:%M:%S")
+                ),
+                format!(
+                    "min: {} ms, p50: {:.0} ms, p90: {:.0} ms, p99: {:.0} ms, max: {} ms",
+                    min, quantiles[0], quantiles[1], quantiles[2], max
+                ),
+                String::new(),
+            ];
+            for (i, &bucket_count) in histogram.iter().enumerate() {
+                let from = min + i as u64 * bucket_size;
+                let to = if i as u64 == BUCKETS - 1 {
+                    max
+                } else {
+                    from + bucket_size
+                };
+                let width = if max_bucket > 0 {
+                    (bucket_count * BAR_WIDTH).div_ceil(max_bucket)
+                } else {
+                    0
+                };
+                lines.push(format!(
+                    "{:>10} - {:<10} ms {:>8} {}",
+                    from,
+                    to,
+                    bucket_count,
+                    "█".repeat(width)
+                ));
+            }
+            if (durations.len() as u64) < count {
+                lines.push(String::new());
+                lines.push(format!(
+                    "NOTE: histogram is built from {} sampled executions",
+                    durations.len()
+                ));
+            }
+            let text = lines.join("\n");
+
             cb_sink
                 .send(Box::new(move |siv: &mut cursive::Cursive| {
                     siv.add_layer(
                         views::Dialog::around(
                             views::LinearLayout::vertical()
-                                .child(views::TextView::new("EXPLAIN PLAN indexes=1").center())
+                                .child(views::TextView::new("Query durations").center())
                                 .child(views::DummyView.fixed_height(1))
-                                .child(views::TextView::new(plan)),
+                                .child(views::TextView::new(text).scrollable()),
                         )
-                        .scrollable(),
+                        .dismiss_button("Close"),
+                    );
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowQuerySlowestProcessors(start, end, query_ids) => {
+            const LIMIT: u64 = 5;
+            let block = clickhouse
+                .get_query_slowest_processors(&query_ids, start, end, LIMIT)
+                .await?;
+            if block.row_count() == 0 {
+                return Err(anyhow!(
+                    "No processors in system.processors_profile_log for the query (log_processors_profiles should be enabled)"
+                ));
+            }
+
+            let mut rows = Vec::new();
+            for i in 0..block.row_count() {
+                rows.push((
+                    block.get::<String, _>(i, "name")?,
+                    block.get::<u64, _>(i, "count")?,
+                    block.get::<f64, _>(i, "elapsed_sec")?,
+                    block.get::<f64, _>(i, "input_wait_sec")?,
+                    block.get::<f64, _>(i, "output_wait_sec")?,
+                ));
+            }
+            let width = rows.iter().map(|r| r.0.len()).max().unwrap().max(4);
+            let mut lines = vec![format!(
+                "{:width$}  {:>6}  {:>10}  {:>10}  {:>10}",
+                "name",
+                "count",
+                "elapsed",
+                "input_wait",
+                "output_wait",
+                width = width
+            )];
+            for (name, count, elapsed, input_wait, output_wait) in rows {
+                lines.push(format!(
+                    "{:width$}  {:>6}  {:>10.3}  {:>10.3}  {:>10.3}",
+                    name,
+                    count,
+                    elapsed,
+                    input_wait,
+                    output_wait,
+                    width = width
+                ));
+            }
+            let text = lines.join("\n");
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(
+                                views::TextView::new(format!(
+                                    "Top {} slowest processors (sec)",
+                                    LIMIT
+                                ))
+                                .center(),
+                            )
+                            .child(views::DummyView.fixed_height(1))
+                            .child(views::TextView::new(text)),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowQueryException(query_id, start, end) => {
+            let block = clickhouse
+                .get_query_exception(&query_id, start, end)
+                .await?;
+            if block.row_count() == 0 {
+                return Err(anyhow!(
+                    "No exception in system.query_log for the query {}",
+                    query_id
+                ));
+            }
+            let exception = block.get::<String, _>(0, "exception")?;
+            let stack_trace = block.get::<String, _>(0, "stack_trace")?;
+            let chain = parse_exception_chain(&exception).join("\n");
+            // Raw text is preserved for copying
+            let raw = if stack_trace.is_empty() {
+                exception
+            } else {
+                format!("{}\n\nStack trace:\n{}", exception, stack_trace)
+            };
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(
+                        views::Dialog::around(
+                            views::LinearLayout::vertical()
+                                .child(views::TextView::new("Exception causes").center())
+                                .child(views::DummyView.fixed_height(1))
+                                .child(views::TextView::new(chain).scrollable()),
+                        )
+                        .button("Copy", move |siv| {
+                            if let Err(err) = copy_to_clipboard(raw.clone()) {
+                                siv.add_layer(views::Dialog::info(err.to_string()));
+                            } else {
+                                siv.pop_layer();
+                            }
+                        })
+                        .dismiss_button("Close"),
+                    );
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowQueryTemporaryTables(query_id, query, start, end) => {
+            let block = clickhouse
+                .get_query_temporary_tables(&query_id, start, end)
+                .await?;
+            let used_tables = if block.row_count() > 0 {
+                block.get::<Vec<String>, _>(0, "temporary_tables")?
+            } else {
+                Vec::new()
+            };
+            let created_tables = get_created_temporary_tables(&query);
+            if used_tables.is_empty() && created_tables.is_empty() {
+                return Err(anyhow!(
+                    "The query {} does not use temporary/external tables",
+                    query_id
+                ));
+            }
+
+            let mut text = StyledString::new();
+            if !used_tables.is_empty() {
+                text.append_styled(
+                    "Temporary/external tables (from system.query_log):\n",
+                    BaseColor::Cyan.light(),
+                );
+                for table in &used_tables {
+                    text.append_plain(format!("  {}\n", table));
+                }
+            }
+            if !created_tables.is_empty() {
+                text.append_styled(
+                    "Temporary tables created by the query:\n",
+                    BaseColor::Cyan.light(),
+                );
+                for table in &created_tables {
+                    text.append_plain(format!("  {}\n", table));
+                }
+            }
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(
+                        views::Dialog::around(views::TextView::new(text).scrollable())
+                            .title(format!("Temporary/external tables of {}", query_id))
+                            .dismiss_button("Close"),
                     );
                 }))
                 .map_err(|_| anyhow!("Cannot send message to UI"))?;
         }
+        Event::ShowQueryThreadsTimeline(start, end, query_ids) => {
+            const BUCKETS: u64 = 60;
+            let end = end.unwrap_or_else(Local::now);
+            let duration_us = (end - start).num_microseconds().unwrap_or_default().max(0) as u64;
+            // Real profiler has 1 sample per second by default, so smaller buckets are useless
+            let bucket_us = (duration_us / BUCKETS).max(1_000_000);
+            let block = clickhouse
+                .get_query_threads_timeline(&query_ids, start, end, bucket_us)
+                .await?;
+            if block.row_count() == 0 {
+                return Err(anyhow!(
+                    "No Real samples in system.trace_log for the query (query_profiler_real_time_period_ns should be enabled)"
+                ));
+            }
+
+            let mut threads = vec![0_u64; (duration_us / bucket_us + 1) as usize];
+            for i in 0..block.row_count() {
+                let bucket = block.get::<u64, _>(i, "bucket")? as usize;
+                if bucket < threads.len() {
+                    threads[bucket] = block.get::<u64, _>(i, "threads")?;
+                }
+            }
+            let max_threads = *threads.iter().max().unwrap();
+            const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+            // Buckets without samples (idle gaps) are rendered as spaces
+            let sparkline = threads
+                .iter()
+                .map(|&t| {
+                    if t == 0 {
+                        return ' ';
+                    }
+                    return BARS[((t - 1) * BARS.len() as u64 / max_threads) as usize];
+                })
+                .collect::<String>();
+            let text = format!(
+                "Max threads: {}, bucket: {} sec\n\n{}\n{}",
+                max_threads,
+                bucket_us as f64 / 1e6,
+                sparkline,
+                "-".repeat(threads.len()),
+            );
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("Threads timeline").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(views::TextView::new(text)),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::CheckPermissions => {
+            let checks = clickhouse.check_permissions().await;
+            let width = checks.iter().map(|(what, _)| what.len()).max().unwrap();
+            let mut lines = Vec::new();
+            for (what, error) in &checks {
+                match error {
+                    // Only the first line, since the error may contain the stacktrace
+                    Some(error) => lines.push(format!(
+                        "FAIL  {:width$}  {}",
+                        what,
+                        error.lines().next().unwrap_or_default(),
+                        width = width
+                    )),
+                    None => lines.push(format!("OK    {}", what)),
+                }
+            }
+            let failed = checks.iter().filter(|(_, error)| error.is_some()).count();
+            lines.push(String::new());
+            if failed > 0 {
+                lines.push(format!(
+                    "{} of {} checks failed, corresponding features will not work",
+                    failed,
+                    checks.len()
+                ));
+            } else {
+                lines.push("All checks passed".to_string());
+            }
+            let text = lines.join("\n");
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(
+                        views::Dialog::around(views::TextView::new(text).scrollable())
+                            .title("Permissions check"),
+                    );
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowMergesTimeline(start, end) => {
+            const BUCKETS: u64 = 60;
+            // Only top tables by merges/mutations time are shown
+            const TABLES: usize = 30;
+            let block = clickhouse.get_merges_timeline(start, end).await?;
+            if block.row_count() == 0 {
+                return Err(anyhow!(
+                    "No merges/mutations in system.part_log for the time interval"
+                ));
+            }
+
+            let start_time = start.timestamp().max(0) as u64;
+            let end_time = end.timestamp().max(0) as u64;
+            let bucket_seconds = ((end_time.saturating_sub(start_time)) / BUCKETS).max(1);
+            let buckets = (end_time.saturating_sub(start_time) / bucket_seconds + 1) as usize;
+
+            // table -> (concurrency per bucket, merges, mutations, total seconds)
+            let mut tables = HashMap::<String, (Vec<u64>, u64, u64, u64)>::new();
+            for i in 0..block.row_count() {
+                let table = block.get::<String, _>(i, "table")?;
+                let event_start = block.get::<u64, _>(i, "start_time")?.max(start_time);
+                let event_end = block.get::<u64, _>(i, "end_time")?.max(event_start);
+                let entry = tables
+                    .entry(table)
+                    .or_insert_with(|| (vec![0; buckets], 0, 0, 0));
+                let first = ((event_start - start_time) / bucket_seconds) as usize;
+                let last = ((event_end - start_time) / bucket_seconds) as usize;
+                for bucket in first..=last.min(buckets - 1) {
+                    entry.0[bucket] += 1;
+                }
+                if block.get::<String, _>(i, "type")? == "MergeParts" {
+                    entry.1 += 1;
+                } else {
+                    entry.2 += 1;
+                }
+                entry.3 += event_end - event_start;
+            }
+
+            let mut tables = tables.into_iter().collect::<Vec<_>>();
+            tables.sort_by(|a, b| b.1 .3.cmp(&a.1 .3));
+            tables.truncate(TABLES);
+
+            let mut total = vec![0_u64; buckets];
+            for (_, (concurrency, _, _, _)) in &tables {
+                for (bucket, value) in concurrency.iter().enumerate() {
+                    total[bucket] += value;
+                }
+            }
+            let max_concurrency = *total.iter().max().unwrap();
+            const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+            let sparkline = |values: &[u64]| {
+                return values
+                    .iter()
+                    .map(|&v| {
+                        if v == 0 {
+                            return ' ';
+                        }
+                        return BARS[((v - 1) * BARS.len() as u64 / max_concurrency) as usize];
+                    })
+                    .collect::<String>();
+            };
+
+            let width = tables
+                .iter()
+                .map(|(table, _)| table.len())
+                .max()
+                .unwrap()
+                .max("total".len());
+            let mut lines = vec![format!(
+                "Max concurrency: {}, bucket: {} sec, {} - {}",
+                max_concurrency,
+                bucket_seconds,
+                start.format("%Y-%m-%d %H:%M:%S"),
+                end.format("%Y-%m-%d %H:%M:%S"),
+            )];
+            lines.push(String::new());
+            lines.push(format!(
+                "{:width$}  {}  merges/mutations",
+                "total",
+                sparkline(&total),
+                width = width
+            ));
+            for (table, (concurrency, merges, mutations, _)) in &tables {
+                lines.push(format!(
+                    "{:width$}  {}  {}/{}",
+                    table,
+                    sparkline(concurrency),
+                    merges,
+                    mutations,
+                    width = width
+                ));
+            }
+            let text = lines.join("\n");
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("Merges/mutations timeline").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(views::TextView::new(text).scrollable()),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ExplainPlanIndexes(database, query) => {
+            let plan = clickhouse
+                .explain_plan_indexes(database.as_str(), query.as_str())
+                .await?
+                .join("\n");
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("EXPLAIN PLAN indexes=1").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(view::LogView::pager(plan)),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::IndexesEstimation(database, query, actual_parts, actual_granules) => {
+            let estimations = clickhouse
+                .get_indexes_estimation(database.as_str(), query.as_str())
+                .await?;
+            let format_ratio = |ratio: Option<(u64, u64)>| {
+                ratio
+                    .map(|(selected, total)| format!("{}/{}", selected, total))
+                    .unwrap_or("n/a".to_string())
+            };
+
+            let mut lines = Vec::new();
+            if estimations.is_empty() {
+                lines.push("No MergeTree sources in the query".to_string());
+            } else {
+                lines.push("Estimated (EXPLAIN PLAN indexes=1):".to_string());
+                for estimation in &estimations {
+                    lines.push(format!(
+                        "  {}: parts {}, granules {}",
+                        estimation.source,
+                        format_ratio(estimation.parts),
+                        format_ratio(estimation.granules),
+                    ));
+                }
+                let estimated_parts: u64 = estimations
+                    .iter()
+                    .filter_map(|e| e.parts.map(|p| p.0))
+                    .sum();
+                let estimated_granules: u64 = estimations
+                    .iter()
+                    .filter_map(|e| e.granules.map(|g| g.0))
+                    .sum();
+                lines.push(String::new());
+                lines.push(format!(
+                    "Estimated total: parts {}, granules {}",
+                    estimated_parts, estimated_granules
+                ));
+            }
+            lines.push(format!(
+                "Actual (ProfileEvents): parts {}, granules {}",
+                actual_parts, actual_granules
+            ));
+            let text = lines.join("\n");
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("Estimated vs actual parts").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(view::LogView::pager(text)),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
         Event::ExecuteQuery(database, query) => {
             let stopwatch = Stopwatch::start_new();
             clickhouse
@@ -356,15 +1099,12 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
             let query = highlight_sql(&query)?;
             cb_sink
                 .send(Box::new(move |siv: &mut cursive::Cursive| {
-                    siv.add_layer(
-                        views::Dialog::around(
-                            views::LinearLayout::vertical()
-                                .child(views::TextView::new("EXPLAIN SYNTAX").center())
-                                .child(views::DummyView.fixed_height(1))
-                                .child(views::TextView::new(query)),
-                        )
-                        .scrollable(),
-                    );
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("EXPLAIN SYNTAX").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(view::LogView::pager(query)),
+                    ));
                 }))
                 .map_err(|_| anyhow!("Cannot send message to UI"))?;
         }
@@ -375,15 +1115,12 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
                 .join("\n");
             cb_sink
                 .send(Box::new(move |siv: &mut cursive::Cursive| {
-                    siv.add_layer(
-                        views::Dialog::around(
-                            views::LinearLayout::vertical()
-                                .child(views::TextView::new("EXPLAIN PLAN").center())
-                                .child(views::DummyView.fixed_height(1))
-                                .child(views::TextView::new(plan)),
-                        )
-                        .scrollable(),
-                    );
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("EXPLAIN PLAN").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(view::LogView::pager(plan)),
+                    ));
                 }))
                 .map_err(|_| anyhow!("Cannot send message to UI"))?;
         }
@@ -394,15 +1131,12 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
                 .join("\n");
             cb_sink
                 .send(Box::new(move |siv: &mut cursive::Cursive| {
-                    siv.add_layer(
-                        views::Dialog::around(
-                            views::LinearLayout::vertical()
-                                .child(views::TextView::new("EXPLAIN PIPELINE").center())
-                                .child(views::DummyView.fixed_height(1))
-                                .child(views::TextView::new(pipeline)),
-                        )
-                        .scrollable(),
-                    );
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("EXPLAIN PIPELINE").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(view::LogView::pager(pipeline)),
+                    ));
                 }))
                 .map_err(|_| anyhow!("Cannot send message to UI"))?;
         }
@@ -422,6 +1156,54 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
                 }))
                 .map_err(|_| anyhow!("Cannot send message to UI"))?;
         }
+        Event::ExplainMermaid(database, query, pipeline) => {
+            let (what, lines) = if pipeline {
+                (
+                    "EXPLAIN PIPELINE",
+                    clickhouse
+                        .explain_pipeline(database.as_str(), query.as_str())
+                        .await?,
+                )
+            } else {
+                (
+                    "EXPLAIN PLAN",
+                    clickhouse
+                        .explain_plan_steps(database.as_str(), query.as_str())
+                        .await?,
+                )
+            };
+            let (title, content) = match explain_to_mermaid(&lines, pipeline) {
+                Ok(mermaid) => {
+                    let path = save_text("chdig-explain-", ".mmd", &mermaid)?;
+                    let copied = match copy_to_clipboard(mermaid.clone()) {
+                        Ok(_) => " and copied to the clipboard",
+                        Err(err) => {
+                            log::warn!("{}", err);
+                            ""
+                        }
+                    };
+                    (
+                        format!("{} as Mermaid (saved to {}{})", what, path, copied),
+                        mermaid,
+                    )
+                }
+                // Fallback to the raw EXPLAIN output
+                Err(err) => (
+                    format!("{} (cannot convert to Mermaid: {})", what, err),
+                    lines.join("\n"),
+                ),
+            };
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new(title).center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(view::LogView::pager(content)),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
         Event::KillQuery(query_id) => {
             let ret = clickhouse.kill_query(query_id.as_str()).await;
             // NOTE: should we do this via cursive, to block the UI?
@@ -437,6 +1219,181 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
                 }))
                 .map_err(|_| anyhow!("Cannot send message to UI"))?;
         }
+        Event::ShowTableFilesystemCache(database, table) => {
+            if !clickhouse.has_system_table("filesystem_cache").await? {
+                return Err(anyhow!(
+                    "system.filesystem_cache is not available (filesystem cache is not supported by the server)"
+                ));
+            }
+            let query = clickhouse.get_table_filesystem_cache_query(&database, &table);
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    let context = siv.user_data::<ContextArc>().unwrap().clone();
+                    let columns = vec![
+                        "file",
+                        "begin",
+                        "end",
+                        "size",
+                        "state",
+                        "hits",
+                        "references",
+                    ];
+                    let view_name = "table_filesystem_cache";
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(
+                                views::TextView::new(format!(
+                                    "Filesystem cache for {}.{}:",
+                                    database, table
+                                ))
+                                .center(),
+                            )
+                            .child(views::DummyView.fixed_height(1))
+                            .child(
+                                view::QueryResultView::new(
+                                    context, view_name, "size", columns, 2, query,
+                                )
+                                .unwrap_or_else(|_| panic!("Cannot get {}", view_name))
+                                .with_name(view_name)
+                                .min_size((160, 20)),
+                            ),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowTableReplicas(database, table) => {
+            let block = clickhouse
+                .get_table_replicas_status(&database, &table)
+                .await?;
+            if block.row_count() == 0 {
+                return Err(anyhow!("{}.{} is not a replicated table", database, table));
+            }
+
+            let mut text = StyledString::plain(format!(
+                "{:30} {:20} {:>6} {:>12} {:>12} {:>8} {:>8}\n",
+                "host", "replica", "active", "log_pointer", "log_max", "queue", "delay"
+            ));
+            for i in 0..block.row_count() {
+                let is_active = block.get::<u8, _>(i, "is_active")? != 0;
+                let absolute_delay = block.get::<u64, _>(i, "absolute_delay")?;
+                let line = format!(
+                    "{:30} {:20} {:>6} {:>12} {:>12} {:>8} {:>8}\n",
+                    block.get::<String, _>(i, "host")?,
+                    block.get::<String, _>(i, "replica_name")?,
+                    if is_active { "yes" } else { "no" },
+                    block.get::<u64, _>(i, "log_pointer")?,
+                    block.get::<u64, _>(i, "log_max_index")?,
+                    block.get::<u32, _>(i, "queue_size")?,
+                    absolute_delay,
+                );
+                if !is_active {
+                    text.append_styled(line, BaseColor::Red.light());
+                } else if absolute_delay > 0 {
+                    text.append_styled(line, BaseColor::Yellow.light());
+                } else {
+                    text.append_plain(line);
+                }
+            }
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(
+                        views::Dialog::around(
+                            views::LinearLayout::vertical()
+                                .child(
+                                    views::TextView::new(format!(
+                                        "Replicas of {}.{}:",
+                                        database, table
+                                    ))
+                                    .center(),
+                                )
+                                .child(views::DummyView.fixed_height(1))
+                                .child(views::TextView::new(text).scrollable()),
+                        )
+                        .dismiss_button("Close"),
+                    );
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::SwitchConnection(connection) => {
+            let options = get_connection_options(&context.lock().unwrap().options, &connection)?;
+            let clickhouse = ClickHouse::new(options.clickhouse.clone()).await?;
+            clickhouse.set_on_reconnect(reconnect_status_callback(cb_sink.clone()));
+            log::info!("Switched to connection {}", connection);
+
+            let status = {
+                let mut context = context.lock().unwrap();
+                context.server_version = clickhouse.version();
+                context.clickhouse = Arc::new(clickhouse);
+                context.options = options;
+                context.connection_status()
+            };
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.call_on_name("main_status", |v: &mut views::TextView| {
+                        v.set_content(status);
+                    });
+                    // Views contains data of the previous server, so start from scratch
+                    let context = siv.user_data::<ContextArc>().unwrap().clone();
+                    siv.drop_main_view();
+                    siv.show_clickhouse_processes(context);
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ReloadDictionary(name) => {
+            clickhouse.reload_dictionary(name.as_deref()).await?;
+            let message = match name {
+                Some(name) => format!("Dictionary {} reloaded", name),
+                None => "All dictionaries reloaded".to_string(),
+            };
+            log::info!("{}", message);
+            // To update last_successful_update_time
+            context.lock().unwrap().trigger_view_refresh();
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.set_statusbar_content(message);
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ExecuteTableCommand(command, database, table) => {
+            let ret = clickhouse
+                .execute_table_command(command, &database, &table)
+                .await;
+            let message;
+            if let Err(err) = ret {
+                message = err.to_string().clone();
+            } else {
+                message = format!("{:?} for {}.{} done", command, database, table);
+            }
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::info(message));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::AlterDetachedPart(database, table, part, attach) => {
+            let ret = clickhouse
+                .alter_detached_part(&database, &table, &part, attach)
+                .await;
+            let message;
+            if let Err(err) = ret {
+                message = err.to_string().clone();
+            } else if attach {
+                message = format!("Part {} attached to {}.{}", part, database, table);
+            } else {
+                message = format!("Detached part {} of {}.{} dropped", part, database, table);
+            }
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::info(message));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::AutoKillQuery(query_id) => match clickhouse.kill_query(query_id.as_str()).await {
+            Ok(_) => log::warn!("Query {} had been killed (--auto-kill-over)", query_id),
+            Err(err) => log::error!("Cannot kill query {}: {}", query_id, err),
+        },
         Event::UpdateSummary => {
             let block = clickhouse.get_summary().await;
             match block {
@@ -459,6 +1416,236 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
                 }
             }
         }
+        Event::ShowThreadsBreakdown => {
+            let summary = clickhouse.get_summary().await?;
+            let threads = summary.threads;
+
+            let mut pools = threads.pools.named();
+            pools.sort_by(|a, b| b.1.cmp(&a.1));
+            let rows = vec![
+                ("OS threads (total)", threads.os_total),
+                ("OS threads (runnable)", threads.os_runnable),
+                ("TCP", threads.tcp),
+                ("HTTP", threads.http),
+                ("Interserver", threads.interserver),
+            ];
+            let width = rows
+                .iter()
+                .chain(pools.iter())
+                .map(|(name, _)| name.len())
+                .max()
+                .unwrap();
+
+            let mut lines = Vec::new();
+            lines.push(format!("{:width$}  {}", "Threads", "Count", width = width));
+            for (name, value) in rows {
+                lines.push(format!("{:width$}  {}", name, value, width = width));
+            }
+            lines.push(String::new());
+            lines.push(format!(
+                "{:width$}  {}",
+                "Pool",
+                "Active tasks",
+                width = width
+            ));
+            for (name, value) in pools {
+                lines.push(format!("{:width$}  {}", name, value, width = width));
+            }
+            let text = lines.join("\n");
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("Threads breakdown").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(views::TextView::new(text)),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowMergeImpact(host, database, table, part) => {
+            let block = clickhouse
+                .get_merge_impact(host.as_deref(), &database, &table, &part)
+                .await?;
+            if block.row_count() == 0 {
+                return Err(anyhow!("Merge {} is not running anymore", part));
+            }
+
+            let fmt_bytes = SizeFormatter::new()
+                .with_base(Base::Base2)
+                .with_style(Style::Abbreviated);
+            let num_parts = block.get::<u64, _>(0, "num_parts")?;
+            let source_parts = block.get::<Vec<String>, _>(0, "source_part_names")?;
+            let partition_id = block.get::<String, _>(0, "partition_id")?;
+            let size = block.get::<u64, _>(0, "total_size_bytes_compressed")?;
+            let progress = block.get::<f64, _>(0, "progress")?;
+            let table_parts = block.get::<u64, _>(0, "table_active_parts")?;
+            let partition_parts = block.get::<u64, _>(0, "partition_active_parts")?;
+            // N source parts are replaced with one merged part
+            let after_merge = |parts: u64| parts.saturating_sub(num_parts) + 1;
+
+            let mut lines = Vec::new();
+            lines.push(format!("Merge: {} ({}.{})", part, database, table));
+            lines.push(format!(
+                "Progress: {:.2} %, size: {}",
+                progress * 100.,
+                fmt_bytes.format(size as i64)
+            ));
+            lines.push(format!("Source parts ({}):", num_parts));
+            for source_part in source_parts {
+                lines.push(format!("  {}", source_part));
+            }
+            lines.push(String::new());
+            lines.push(format!(
+                "Active parts in partition {}: {} -> {}",
+                partition_id,
+                partition_parts,
+                after_merge(partition_parts)
+            ));
+            lines.push(format!(
+                "Active parts in table: {} -> {}",
+                table_parts,
+                after_merge(table_parts)
+            ));
+            let text = lines.join("\n");
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("Merge impact").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(views::TextView::new(text).scrollable()),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::ShowSettingsProfile(user, settings) => {
+            let mut lines = Vec::new();
+            // Access entities may not be available for the current user (requires SHOW ACCESS
+            // grant), or profiles may not be used at all, so this is not an error
+            match clickhouse
+                .get_settings_profile_elements(user.as_str())
+                .await
+            {
+                Err(err) => {
+                    lines.push(format!("Settings profiles are not available: {}", err));
+                }
+                Ok(block) => {
+                    let mut profiles = Vec::<String>::new();
+                    let mut constraints = Vec::<String>::new();
+                    let or_dash = |v: &str| if v.is_empty() { "-" } else { v }.to_string();
+                    for i in 0..block.row_count() {
+                        let profile = block.get::<String, _>(i, "profile")?;
+                        let inherit_profile = block.get::<String, _>(i, "inherit_profile")?;
+                        let setting_name = block.get::<String, _>(i, "setting_name")?;
+                        let value = block.get::<String, _>(i, "value")?;
+                        let min = block.get::<String, _>(i, "min")?;
+                        let max = block.get::<String, _>(i, "max")?;
+                        let writability = block.get::<String, _>(i, "writability")?;
+
+                        if !inherit_profile.is_empty() && !profiles.contains(&inherit_profile) {
+                            profiles.push(inherit_profile);
+                        }
+                        if setting_name.is_empty() {
+                            continue;
+                        }
+                        if min.is_empty() && max.is_empty() && writability.is_empty() {
+                            continue;
+                        }
+
+                        let query_value = settings.get(&setting_name);
+                        // Constraints do not allow to change the setting outside of the
+                        // bounds, so if the value is at the bound, it is likely that the
+                        // requested value did not take effect
+                        let status = match query_value {
+                            Some(v) if !min.is_empty() && *v == min => "at min bound",
+                            Some(v) if !max.is_empty() && *v == max => "at max bound",
+                            Some(_) => "",
+                            None if !value.is_empty() => "profile default",
+                            None => "",
+                        };
+                        constraints.push(format!(
+                            "  {}: {} (profile: {}, value: {}, min: {}, max: {}, {}) {}",
+                            setting_name,
+                            query_value.map(|v| v.as_str()).unwrap_or("-"),
+                            or_dash(&profile),
+                            or_dash(&value),
+                            or_dash(&min),
+                            or_dash(&max),
+                            if writability.is_empty() {
+                                "WRITABLE"
+                            } else {
+                                writability.as_str()
+                            },
+                            status,
+                        ));
+                    }
+
+                    if profiles.is_empty() {
+                        lines.push(format!("No settings profiles for user {}", user));
+                    } else {
+                        lines.push(format!(
+                            "Profiles of user {}: {}",
+                            user,
+                            profiles.join(", ")
+                        ));
+                    }
+                    if constraints.is_empty() {
+                        lines.push("No settings constraints".to_string());
+                    } else {
+                        lines.push(String::new());
+                        lines.push("Constraints (setting: query value (...) status):".to_string());
+                        lines.extend(constraints);
+                    }
+                }
+            }
+            let text = lines.join("\n");
+
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("Settings profile").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(view::LogView::pager(text)),
+                    ));
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
+        Event::CustomQueryView(query, sort_by) => {
+            // The query is executed on each refresh, so only SELECT queries are allowed, and
+            // they are executed with readonly=1 just in case
+            if !is_select_query(&query) {
+                return Err(anyhow!(
+                    "Only SELECT queries are allowed for custom query view"
+                ));
+            }
+            if let Some(clause) = get_format_or_settings_clause(&query) {
+                return Err(anyhow!(
+                    "{} is not allowed for custom query view (the query is executed as a subquery)",
+                    clause
+                ));
+            }
+            let query = format!("SELECT * FROM ({}) SETTINGS readonly = 1", query);
+            // Execute the query once to validate it and to get the columns
+            let block = clickhouse.execute(query.as_str()).await?;
+            let columns = view::QueryResultView::get_columns(&block)?;
+            if !sort_by.is_empty() && !columns.contains(&sort_by) {
+                return Err(anyhow!(
+                    "No column {} for sort (available: {})",
+                    sort_by,
+                    columns.join(", ")
+                ));
+            }
+            let context = context.clone();
+            cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.show_custom_query_view(context, query, columns, sort_by);
+                }))
+                .map_err(|_| anyhow!("Cannot send message to UI"))?;
+        }
         Event::ViewQuery(view_name, query) => {
             let block = clickhouse.execute(query.as_str()).await?;
             cb_sink
@@ -477,3 +1664,46 @@ async fn process_event(context: ContextArc, event: Event, need_clear: &mut bool)
 
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_select_query() {
+        assert!(is_select_query("SELECT 1 + 2"));
+        assert!(is_select_query("with 1 AS x SELECT x"));
+        assert!(is_select_query(" (SELECT _part FROM t)"));
+        assert!(!is_select_query("INSERT INTO t SELECT 1"));
+        assert!(!is_select_query("KILL QUERY WHERE 1"));
+    }
+
+    #[test]
+    fn test_is_periodic() {
+        assert!(Event::UpdateSummary.is_periodic());
+        assert!(Event::ViewQuery("system.merges", "SELECT 1".to_string()).is_periodic());
+        assert!(!Event::KillQuery("id".to_string()).is_periodic());
+        assert!(!Event::CustomQueryView("SELECT 1".to_string(), String::new()).is_periodic());
+        assert!(!Event::ReloadDictionary(None).is_periodic());
+    }
+
+    #[test]
+    fn test_get_format_or_settings_clause() {
+        assert_eq!(
+            get_format_or_settings_clause("SELECT round(x, 2) FROM t"),
+            None
+        );
+        assert_eq!(
+            get_format_or_settings_clause("SELECT name FROM system.settings"),
+            None
+        );
+        assert_eq!(
+            get_format_or_settings_clause("SELECT 1 FORMAT JSON").as_deref(),
+            Some("FORMAT")
+        );
+        assert_eq!(
+            get_format_or_settings_clause("SELECT 1 settings max_threads = 1").as_deref(),
+            Some("SETTINGS")
+        );
+    }
+}
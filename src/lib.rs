@@ -2,12 +2,20 @@ mod actions;
 mod utils;
 
 // utils
+pub use utils::copy_to_clipboard;
 pub use utils::edit_query;
+pub use utils::explain_to_mermaid;
 #[cfg(not(target_family = "windows"))]
 pub use utils::fuzzy_actions;
 pub use utils::get_query;
+pub use utils::get_query_with_set_statements;
 pub use utils::highlight_sql;
 pub use utils::open_graph_in_browser;
+pub use utils::parse_exception_chain;
+pub use utils::save_json;
+pub use utils::save_rows;
+pub use utils::save_text;
+pub use utils::save_tsv;
 
 // actions
 pub use actions::ActionDescription;
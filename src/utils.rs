@@ -1,10 +1,12 @@
 use anyhow::{Context, Error, Result};
 use cursive::utils::markup::StyledString;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io::Write;
 use std::process::{Command, Stdio};
+use std::sync::Mutex;
 use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
 use tempfile::Builder;
 use urlencoding::encode;
@@ -84,6 +86,28 @@ pub fn get_query(query: &String, settings: &HashMap<String, String>) -> String {
     return ret;
 }
 
+/// Query prepended with SET statements for the settings (to reproduce the query in
+/// clickhouse-client)
+pub fn get_query_with_set_statements(query: &str, settings: &HashMap<String, String>) -> String {
+    let mut settings = settings.iter().collect::<Vec<(&String, &String)>>();
+    settings.sort();
+
+    let mut ret = String::new();
+    for (name, value) in settings {
+        if value.parse::<f64>().is_ok() {
+            ret.push_str(&format!("SET {}={};\n", name, value));
+        } else {
+            ret.push_str(&format!(
+                "SET {}='{}';\n",
+                name,
+                value.replace('\\', "\\\\").replace('\'', "\\\'")
+            ));
+        }
+    }
+    ret.push_str(query);
+    return ret;
+}
+
 pub fn edit_query(query: &String, settings: &HashMap<String, String>) -> Result<String> {
     let mut tmp_file = Builder::new()
         .prefix("chdig-query-")
@@ -112,6 +136,263 @@ pub fn edit_query(query: &String, settings: &HashMap<String, String>) -> Result<
     return Ok(query);
 }
 
+/// Save the value as pretty JSON into a (persistent) temporary file, returns the path.
+pub fn save_json<T: Serialize>(prefix: &str, value: &T) -> Result<String> {
+    let tmp_file = Builder::new()
+        .prefix(prefix)
+        .suffix(".json")
+        .rand_bytes(5)
+        .tempfile()?;
+    let (mut file, path) = tmp_file.keep()?;
+    serde_json::to_writer_pretty(&mut file, value)?;
+    file.flush()?;
+    return Ok(path.display().to_string());
+}
+
+/// Save rows as TSV (with header) into a (persistent) temporary file, returns the path.
+pub fn save_tsv(prefix: &str, header: &[String], rows: &[Vec<String>]) -> Result<String> {
+    let tmp_file = Builder::new()
+        .prefix(prefix)
+        .suffix(".tsv")
+        .rand_bytes(5)
+        .tempfile()?;
+    let (mut file, path) = tmp_file.keep()?;
+    // TabSeparated escaping, so that the file can be read back with clickhouse-local
+    let escape = |value: &String| -> String {
+        return value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n");
+    };
+    writeln!(
+        file,
+        "{}",
+        header.iter().map(escape).collect::<Vec<_>>().join("\t")
+    )?;
+    for row in rows {
+        writeln!(
+            file,
+            "{}",
+            row.iter().map(escape).collect::<Vec<_>>().join("\t")
+        )?;
+    }
+    file.flush()?;
+    return Ok(path.display().to_string());
+}
+
+/// Save text into a (persistent) temporary file, returns the path.
+pub fn save_text(prefix: &str, suffix: &str, text: &str) -> Result<String> {
+    let tmp_file = Builder::new()
+        .prefix(prefix)
+        .suffix(suffix)
+        .rand_bytes(5)
+        .tempfile()?;
+    let (mut file, path) = tmp_file.keep()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+    return Ok(path.display().to_string());
+}
+
+/// Convert indented EXPLAIN PLAN/PIPELINE output into Mermaid flowchart definition.
+///
+/// In EXPLAIN PIPELINE processors are printed at the same level as their step (i.e.
+/// "(Expression)" followed by "ExpressionTransform"), so for pipeline they are chained.
+pub fn explain_to_mermaid(lines: &[String], pipeline: bool) -> Result<String> {
+    let mut nodes = Vec::<String>::new();
+    let mut edges = Vec::<(usize, usize)>::new();
+    // (depth, node) of the current path in the tree
+    let mut stack = Vec::<(usize, usize)>::new();
+
+    for line in lines {
+        let label = line.trim();
+        if label.is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        if indent % 2 != 0 {
+            return Err(Error::msg(format!("Unexpected indentation: '{}'", line)));
+        }
+        let depth = indent / 2;
+
+        let is_processor = pipeline && !label.starts_with('(');
+        if is_processor {
+            while stack.last().is_some_and(|(d, _)| *d > depth) {
+                stack.pop();
+            }
+            // Processor of the step (that should be printed right before)
+            if stack.last().is_some_and(|(d, _)| *d == depth) {
+                let (_, step) = stack.pop().unwrap();
+                edges.push((step, nodes.len()));
+            } else if let Some((_, parent)) = stack.last() {
+                edges.push((*parent, nodes.len()));
+            }
+        } else {
+            while stack.last().is_some_and(|(d, _)| *d >= depth) {
+                stack.pop();
+            }
+            if let Some((_, parent)) = stack.last() {
+                edges.push((*parent, nodes.len()));
+            } else if !nodes.is_empty() && depth > 0 {
+                return Err(Error::msg(format!("Cannot find parent for '{}'", label)));
+            }
+        }
+        stack.push((depth, nodes.len()));
+        nodes.push(label.to_string());
+    }
+
+    if nodes.is_empty() {
+        return Err(Error::msg("EXPLAIN output is empty"));
+    }
+
+    let mut mermaid = String::from("flowchart TD\n");
+    for (i, node) in nodes.iter().enumerate() {
+        mermaid.push_str(&format!(
+            "    n{}[\"{}\"]\n",
+            i,
+            node.replace('"', "#quot;")
+        ));
+    }
+    for (from, to) in edges {
+        mermaid.push_str(&format!("    n{} --> n{}\n", from, to));
+    }
+    return Ok(mermaid);
+}
+
+/// Save rows into the file, the format (CSV, TSV or JSON) is detected by the extension.
+pub fn save_rows(path: &str, header: &[String], rows: &[Vec<serde_json::Value>]) -> Result<()> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    // Strings as is, other values in JSON representation
+    let raw = |value: &serde_json::Value| -> String {
+        return match value {
+            serde_json::Value::String(value) => value.clone(),
+            serde_json::Value::Null => "".to_string(),
+            _ => value.to_string(),
+        };
+    };
+
+    let mut file = fs::File::create(path).context(format!("Cannot create {}", path))?;
+    match extension.as_str() {
+        "csv" => {
+            let escape = |value: String| -> String {
+                if value.contains([',', '"', '\n', '\r']) {
+                    return format!("\"{}\"", value.replace('"', "\"\""));
+                }
+                return value;
+            };
+            writeln!(
+                file,
+                "{}",
+                header
+                    .iter()
+                    .map(|h| escape(h.clone()))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )?;
+            for row in rows {
+                writeln!(
+                    file,
+                    "{}",
+                    row.iter()
+                        .map(|v| escape(raw(v)))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )?;
+            }
+        }
+        "tsv" => {
+            let escape = |value: String| -> String {
+                return value
+                    .replace('\\', "\\\\")
+                    .replace('\t', "\\t")
+                    .replace('\n', "\\n");
+            };
+            writeln!(
+                file,
+                "{}",
+                header
+                    .iter()
+                    .map(|h| escape(h.clone()))
+                    .collect::<Vec<_>>()
+                    .join("\t")
+            )?;
+            for row in rows {
+                writeln!(
+                    file,
+                    "{}",
+                    row.iter()
+                        .map(|v| escape(raw(v)))
+                        .collect::<Vec<_>>()
+                        .join("\t")
+                )?;
+            }
+        }
+        "json" => {
+            let objects = rows
+                .iter()
+                .map(|row| {
+                    return serde_json::Value::Object(
+                        header.iter().cloned().zip(row.iter().cloned()).collect(),
+                    );
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_writer_pretty(&mut file, &objects)?;
+        }
+        _ => {
+            return Err(Error::msg(format!(
+                "Unknown format for {} (supported extensions: csv, tsv, json)",
+                path
+            )));
+        }
+    }
+    file.flush()?;
+    return Ok(());
+}
+
+/// Split the exception message into the chain of causes.
+///
+/// Nested exceptions (i.e. from remote servers) are concatenated as "Received from host.
+/// DB::Exception: ...", while the context is appended via ": While executing ...", so each of
+/// them is one level deeper.
+pub fn parse_exception_chain(exception: &str) -> Vec<String> {
+    let mut chain = Vec::new();
+    for (level, cause) in exception.split("DB::Exception: ").enumerate() {
+        let cause = cause.trim();
+        if cause.is_empty() {
+            continue;
+        }
+        for (i, part) in cause.split(": While ").enumerate() {
+            let part = if i > 0 {
+                format!("While {}", part.trim())
+            } else {
+                part.trim().to_string()
+            };
+            chain.push(format!("{}{}", "  ".repeat(level), part));
+        }
+    }
+    return chain;
+}
+
+// On X11/Wayland the clipboard content is served by the owner, so the content is lost once
+// the Clipboard is dropped, hence it is kept for the lifetime of the process.
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+pub fn copy_to_clipboard(text: String) -> Result<()> {
+    let mut clipboard = CLIPBOARD.lock().unwrap();
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new().context("Cannot access the clipboard")?);
+    }
+    clipboard
+        .as_mut()
+        .unwrap()
+        .set_text(text)
+        .context("Cannot copy to the clipboard")?;
+    return Ok(());
+}
+
 pub fn open_graph_in_browser(graph: String) -> Result<()> {
     let graph = encode(&graph);
     Command::new("xdg-open")
@@ -194,6 +194,12 @@ impl LogViewBase {
         self.compute_rows();
     }
 
+    fn push_text(&mut self, text: StyledString) {
+        self.content.append(text);
+        self.needs_relayout = true;
+        self.compute_rows();
+    }
+
     fn compute_rows(&mut self) {
         let width = if self.wrap {
             // For scrolling we need to subtract some padding
@@ -368,6 +374,12 @@ impl LogView {
             .on_pre_event_inner('k', move |v, _| reset_search(v, &Event::Key(Key::Up)))
             .on_pre_event_inner('g', move |v, _| reset_search(v, &Event::Key(Key::Home)))
             .on_pre_event_inner('G', move |v, _| reset_search(v, &Event::Key(Key::End)))
+            .on_pre_event_inner(Event::CtrlChar('d'), move |v, _| {
+                reset_search(v, &Event::Key(Key::PageDown))
+            })
+            .on_pre_event_inner(Event::CtrlChar('u'), move |v, _| {
+                reset_search(v, &Event::Key(Key::PageUp))
+            })
             .on_event_inner('-', move |_, _| {
                 return Some(EventResult::Consumed(Some(Callback::from_fn(show_options))));
             })
@@ -399,6 +411,44 @@ impl LogView {
     pub fn push_logs(&mut self, logs: &[LogEntry]) {
         self.inner_view.get_inner_mut().get_mut().push_logs(logs);
     }
+
+    pub fn clear(&mut self) {
+        let mut base = self.inner_view.get_inner_mut().get_mut();
+        base.content = StyledString::new();
+        base.matched_row = None;
+        base.needs_relayout = true;
+        base.compute_rows();
+    }
+
+    /// Keep the view scrolled to the last line on new logs (or keep the current row otherwise)
+    pub fn set_stick_to_bottom(&mut self, stick: bool) {
+        let mut base = self.inner_view.get_inner_mut().get_mut();
+        if stick {
+            base.scroll_core
+                .set_scroll_strategy(ScrollStrategy::StickToBottom);
+        } else {
+            base.scroll_core
+                .set_scroll_strategy(ScrollStrategy::KeepRow);
+        }
+    }
+
+    /// Pager for arbitrary text (long EXPLAIN output, queries, ...), with the same navigation
+    /// and search as for logs.
+    pub fn pager(text: impl Into<StyledString>) -> Self {
+        return Self::pager_with_wrap(text, false);
+    }
+
+    /// Same as pager(), but with line wrapping (can be toggled with "-S" as well).
+    pub fn pager_with_wrap(text: impl Into<StyledString>, wrap: bool) -> Self {
+        let mut v = LogView::new(false, wrap);
+        {
+            let mut base = v.inner_view.get_inner_mut().get_mut();
+            base.scroll_core
+                .set_scroll_strategy(ScrollStrategy::KeepRow);
+            base.push_text(text.into());
+        }
+        return v;
+    }
 }
 
 impl View for LogViewBase {
@@ -1,29 +1,48 @@
 use crate::{
     interpreter::{
-        clickhouse::TraceType,
-        options::{parse_datetime_or_date, ChDigViews},
-        ContextArc, WorkerEvent,
+        clickhouse::{escape_string, TableCommand, TraceType},
+        options::{
+            get_connections, get_summary_baselines, parse_datetime_or_date, read_summary_baseline,
+            save_summary_baseline, save_time_frame, ChDigViews,
+        },
+        Context, ContextArc, WorkerEvent,
     },
     view::{self, TextLogView},
 };
 use anyhow::Result;
 #[cfg(not(target_family = "windows"))]
 use chdig::fuzzy_actions;
+use chdig::{copy_to_clipboard, save_rows};
+use chrono::{DateTime, Local};
 use cursive::{
-    event::{Event, EventResult, Key},
+    event::{Callback, Event, EventResult, Key},
     theme::{BaseColor, Color, ColorStyle, Effect, PaletteColor, Style, Theme},
     utils::{markup::StyledString, span::SpannedString},
     view::View,
     view::{IntoBoxedView, Nameable, Resizable},
     views::{
-        Dialog, DummyView, EditView, FixedLayout, Layer, LinearLayout, OnEventView, OnLayoutView,
-        SelectView, TextContent, TextView,
+        Dialog, DummyView, EditView, FixedLayout, Layer, LinearLayout, NamedView, OnEventView,
+        OnLayoutView, SelectView, TextContent, TextView,
     },
     Cursive, {Rect, Vec2},
 };
 use cursive_flexi_logger_view::toggle_flexi_logger_debug_console;
+use size::{Base, SizeFormatter, Style as SizeStyle};
 use std::collections::HashMap;
 
+// Current time and the time of the last update of the views (in --timezone)
+fn get_clock_text(context: &Context) -> String {
+    let timezone = context.options.view.timezone;
+    let format = |time: DateTime<Local>| match timezone {
+        Some(timezone) => time.with_timezone(&timezone).format("%H:%M:%S").to_string(),
+        None => time.format("%H:%M:%S").to_string(),
+    };
+    return match context.get_last_update() {
+        Some((_, updated)) => format!("{} (updated {})", format(Local::now()), format(updated)),
+        None => format(Local::now()),
+    };
+}
+
 fn make_menu_text() -> StyledString {
     let mut text = StyledString::new();
 
@@ -60,17 +79,30 @@ pub trait Navigation {
     #[cfg(not(target_family = "windows"))]
     fn show_fuzzy_actions(&mut self);
     fn show_server_flamegraph(&mut self, tui: bool);
+    fn show_global_search(&mut self);
+    fn show_connections(&mut self);
+    fn save_summary_baseline(&mut self);
+    fn show_summary_baselines(&mut self);
+    fn show_merges_timeline(&mut self);
+    fn global_search(&mut self, context: ContextArc, text: &str);
 
     fn drop_main_view(&mut self);
     fn set_main_view<V: IntoBoxedView + 'static>(&mut self, view: V);
 
     fn statusbar(&mut self, main_content: impl Into<SpannedString<Style>>);
     fn set_statusbar_content(&mut self, content: impl Into<SpannedString<Style>>);
+    // Render error of the action (dialog, or log/statusbar for --quiet-errors)
+    fn show_action_error(&mut self, err: anyhow::Error);
 
     fn show_clickhouse_processes(&mut self, context: ContextArc);
     fn show_clickhouse_slow_query_log(&mut self, context: ContextArc);
     fn show_clickhouse_last_query_log(&mut self, context: ContextArc);
     fn show_clickhouse_merges(&mut self, context: ContextArc);
+    fn show_clickhouse_parts(&mut self, context: ContextArc);
+    fn show_clickhouse_detached_parts(&mut self, context: ContextArc);
+    fn show_clickhouse_disks(&mut self, context: ContextArc);
+    fn show_clickhouse_zookeeper(&mut self, context: ContextArc);
+    fn show_clickhouse_part_log(&mut self, context: ContextArc);
     fn show_clickhouse_mutations(&mut self, context: ContextArc);
     fn show_clickhouse_replication_queue(&mut self, context: ContextArc);
     fn show_clickhouse_replicated_fetches(&mut self, context: ContextArc);
@@ -79,6 +111,15 @@ pub trait Navigation {
     fn show_clickhouse_backups(&mut self, context: ContextArc);
     fn show_clickhouse_dictionaries(&mut self, context: ContextArc);
     fn show_clickhouse_server_logs(&mut self, context: ContextArc);
+    fn show_clickhouse_dashboard(&mut self, context: ContextArc);
+    fn show_custom_query_view_dialog(&mut self);
+    fn show_custom_query_view(
+        &mut self,
+        context: ContextArc,
+        query: String,
+        columns: Vec<String>,
+        sort_by: String,
+    );
 
     #[allow(clippy::too_many_arguments)]
     fn show_query_result_view<F>(
@@ -93,6 +134,18 @@ pub trait Navigation {
         settings: &HashMap<&str, &str>,
     ) where
         F: Fn(&mut Cursive, view::QueryResultRow) + Send + Sync + 'static;
+    #[allow(clippy::too_many_arguments)]
+    fn show_query_result_view_for_query<F>(
+        &mut self,
+        context: ContextArc,
+        name: &'static str,
+        sort_by: &str,
+        columns: Vec<&str>,
+        columns_to_compare: usize,
+        on_submit: Option<F>,
+        query: String,
+    ) where
+        F: Fn(&mut Cursive, view::QueryResultRow) + Send + Sync + 'static;
 
     // TODO: move into separate trait
     fn call_on_name_or_render_error<V, F>(&mut self, name: &str, callback: F)
@@ -103,6 +156,671 @@ pub trait Navigation {
 
 const QUERY_RESULT_VIEW_NOP_CALLBACK: Option<fn(&mut Cursive, view::QueryResultRow)> = None;
 
+#[derive(Debug, PartialEq)]
+enum GlobalSearchTarget {
+    QueryId,
+    // i.e. all_1_1_0, 202401_1_10_2_5
+    Part,
+    // database.table
+    Table,
+    Unknown,
+}
+fn get_global_search_target(text: &str) -> GlobalSearchTarget {
+    let is_uuid = text.len() == 36
+        && text.chars().enumerate().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        });
+    if is_uuid {
+        return GlobalSearchTarget::QueryId;
+    }
+
+    let part = text.split('_').collect::<Vec<&str>>();
+    if (part.len() == 4 || part.len() == 5)
+        && !part[0].is_empty()
+        && part[1..]
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+    {
+        return GlobalSearchTarget::Part;
+    }
+
+    if let Some((database, table)) = text.split_once('.') {
+        if !database.is_empty()
+            && !table.is_empty()
+            && !table.contains('.')
+            && !text.contains(char::is_whitespace)
+        {
+            return GlobalSearchTarget::Table;
+        }
+    }
+
+    return GlobalSearchTarget::Unknown;
+}
+
+// Action for any QueryResultView that has "database" and "table" columns
+fn show_table_merges_and_mutations(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let database = v.get_selected_column("database")?.to_string();
+    let table = v.get_selected_column("table")?.to_string();
+    let context = v.get_context();
+
+    let (query, cluster, cb_sink) = {
+        let context = context.lock().unwrap();
+        (
+            context
+                .clickhouse
+                .get_table_merges_and_mutations_query(&database, &table),
+            context.options.clickhouse.cluster.is_some(),
+            context.cb_sink.clone(),
+        )
+    };
+    let mut columns = vec![
+        "type", "name", "elapsed", "progress", "parts", "memory", "reason",
+    ];
+    let mut columns_to_compare = 2;
+    if cluster {
+        columns.insert(0, "host");
+        columns_to_compare += 1;
+    }
+
+    let view_name = "table_merges_and_mutations";
+    cb_sink
+        .send(Box::new(move |siv: &mut Cursive| {
+            siv.add_layer(Dialog::around(
+                LinearLayout::vertical()
+                    .child(
+                        TextView::new(format!("Merges/mutations for {}.{}:", database, table))
+                            .center(),
+                    )
+                    .child(DummyView.fixed_height(1))
+                    .child(
+                        view::QueryResultView::new(
+                            context,
+                            view_name,
+                            "elapsed",
+                            columns,
+                            columns_to_compare,
+                            query,
+                        )
+                        .unwrap_or_else(|_| panic!("Cannot get {}", view_name))
+                        .with_name(view_name)
+                        // TODO: autocalculate
+                        .min_size((160, 20)),
+                    ),
+            ));
+        }))
+        .unwrap();
+
+    return Ok(Some(EventResult::consumed()));
+}
+
+// Action for any QueryResultView that has "database" and "table" columns
+fn show_table_columns_compression(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let database = v.get_selected_column("database")?.to_string();
+    let table = v.get_selected_column("table")?.to_string();
+    let context = v.get_context();
+
+    let (query, cb_sink) = {
+        let context = context.lock().unwrap();
+        (
+            context
+                .clickhouse
+                .get_table_columns_compression_query(&database, &table),
+            context.cb_sink.clone(),
+        )
+    };
+    let columns = vec![
+        "column",
+        "type",
+        "codec",
+        "compressed",
+        "uncompressed",
+        "ratio",
+    ];
+
+    let view_name = "table_columns_compression";
+    cb_sink
+        .send(Box::new(move |siv: &mut Cursive| {
+            siv.add_layer(Dialog::around(
+                LinearLayout::vertical()
+                    .child(
+                        TextView::new(format!("Columns compression for {}.{}:", database, table))
+                            .center(),
+                    )
+                    .child(DummyView.fixed_height(1))
+                    .child(
+                        view::QueryResultView::new(context, view_name, "ratio", columns, 1, query)
+                            .unwrap_or_else(|_| panic!("Cannot get {}", view_name))
+                            .with_name(view_name)
+                            // TODO: autocalculate
+                            .min_size((140, 20)),
+                    ),
+            ));
+        }))
+        .unwrap();
+
+    return Ok(Some(EventResult::consumed()));
+}
+
+// Submit callback for system.parts view
+fn show_part_log(
+    siv: &mut Cursive,
+    context: ContextArc,
+    database: String,
+    table: String,
+    part: String,
+) {
+    let (query, cluster) = {
+        let context = context.lock().unwrap();
+        (
+            context
+                .clickhouse
+                .get_part_log_query(&database, &table, &part),
+            context.options.clickhouse.cluster.is_some(),
+        )
+    };
+    let mut columns = vec![
+        "event_time",
+        "type",
+        "name",
+        "duration_ms",
+        "rows",
+        "size",
+        "merged_from",
+        "exception",
+    ];
+    let mut columns_to_compare = 3;
+    if cluster {
+        columns.insert(0, "host");
+        columns_to_compare += 1;
+    }
+
+    let view_name = "part_log";
+    siv.add_layer(Dialog::around(
+        LinearLayout::vertical()
+            .child(TextView::new(format!("Part log for {}.{} {}:", database, table, part)).center())
+            .child(DummyView.fixed_height(1))
+            .child(
+                view::QueryResultView::new(
+                    context,
+                    view_name,
+                    "event_time",
+                    columns,
+                    columns_to_compare,
+                    query,
+                )
+                .unwrap_or_else(|_| panic!("Cannot get {}", view_name))
+                .with_name(view_name)
+                // TODO: autocalculate
+                .min_size((160, 20)),
+            ),
+    ));
+}
+
+fn show_table_filesystem_cache(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let database = v.get_selected_column("database")?.to_string();
+    let table = v.get_selected_column("table")?.to_string();
+    v.get_context()
+        .lock()
+        .unwrap()
+        .worker
+        .send(WorkerEvent::ShowTableFilesystemCache(database, table));
+    return Ok(Some(EventResult::consumed()));
+}
+
+fn show_table_replicas(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let database = v.get_selected_column("database")?.to_string();
+    let table = v.get_selected_column("table")?.to_string();
+    v.get_context()
+        .lock()
+        .unwrap()
+        .worker
+        .send(WorkerEvent::ShowTableReplicas(database, table));
+    return Ok(Some(EventResult::consumed()));
+}
+
+// Action for any QueryResultView that has "database" and "table" columns
+fn execute_table_command(v: &mut dyn View, command: TableCommand) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let database = v.get_selected_column("database")?.to_string();
+    let table = v.get_selected_column("table")?.to_string();
+    let context = v.get_context();
+
+    let title = match command {
+        TableCommand::StopMerges => {
+            format!("Are you sure you want to STOP MERGES for {}.{}", database, table)
+        }
+        TableCommand::StartMerges => {
+            format!("Are you sure you want to START MERGES for {}.{}", database, table)
+        }
+        TableCommand::OptimizeFinal => format!(
+            "DANGEROUS: OPTIMIZE FINAL rewrites all parts of {}.{} (heavy IO/CPU, may take hours)\n\nAre you sure you want to run it?",
+            database, table
+        ),
+    };
+    let context_copy = context.clone();
+    context
+        .lock()
+        .unwrap()
+        .cb_sink
+        .send(Box::new(move |siv: &mut Cursive| {
+            siv.add_layer(
+                Dialog::text(title)
+                    .button("Yes, I'm sure", move |s| {
+                        context_copy
+                            .lock()
+                            .unwrap()
+                            .worker
+                            .send(WorkerEvent::ExecuteTableCommand(
+                                command,
+                                database.clone(),
+                                table.clone(),
+                            ));
+                        s.pop_layer();
+                    })
+                    .button("Cancel", |s| {
+                        s.pop_layer();
+                    }),
+            );
+        }))
+        .unwrap();
+
+    return Ok(Some(EventResult::consumed()));
+}
+
+// Action for system.dictionaries view
+fn reload_dictionary(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let database = v.get_selected_column("database")?.to_string();
+    let name = v.get_selected_column("name")?.to_string();
+    // Dictionaries from config files does not have database (and may contain dots in name)
+    let name = if database.is_empty() {
+        format!("`{}`", name)
+    } else {
+        format!("`{}`.`{}`", database, name)
+    };
+    v.get_context()
+        .lock()
+        .unwrap()
+        .worker
+        .send(WorkerEvent::ReloadDictionary(Some(name)));
+    return Ok(Some(EventResult::consumed()));
+}
+
+// Action for system.merges view
+fn show_merge_impact(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let database = v.get_selected_column("database")?.to_string();
+    let table = v.get_selected_column("table")?.to_string();
+    let part = v.get_selected_column("part")?.to_string();
+    let context = v.get_context();
+    let mut context = context.lock().unwrap();
+    // host column exists only in --cluster mode
+    let host = if context.options.clickhouse.cluster.is_some() {
+        Some(v.get_selected_column("host")?.to_string())
+    } else {
+        None
+    };
+    context
+        .worker
+        .send(WorkerEvent::ShowMergeImpact(host, database, table, part));
+    return Ok(Some(EventResult::consumed()));
+}
+
+// Action for system.detached_parts view (ATTACH PART or DROP DETACHED PART)
+fn alter_detached_part(v: &mut dyn View, attach: bool) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let database = v.get_selected_column("database")?.to_string();
+    let table = v.get_selected_column("table")?.to_string();
+    let part = v.get_selected_column("name")?.to_string();
+    let context = v.get_context();
+
+    let event =
+        WorkerEvent::AlterDetachedPart(database.clone(), table.clone(), part.clone(), attach);
+    if attach {
+        context.lock().unwrap().worker.send(event);
+        return Ok(Some(EventResult::consumed()));
+    }
+
+    let context_copy = context.clone();
+    context
+        .lock()
+        .unwrap()
+        .cb_sink
+        .send(Box::new(move |siv: &mut Cursive| {
+            siv.add_layer(
+                Dialog::new()
+                    .title(format!(
+                        "Are you sure you want to DROP DETACHED PART {} of {}.{}",
+                        part, database, table
+                    ))
+                    .button("Yes, I'm sure", move |s| {
+                        context_copy.lock().unwrap().worker.send(event.clone());
+                        s.pop_layer();
+                    })
+                    .button("Cancel", |s| {
+                        s.pop_layer();
+                    }),
+            );
+        }))
+        .unwrap();
+
+    return Ok(Some(EventResult::consumed()));
+}
+
+const ZOOKEEPER_COLUMNS: [&str; 5] = ["name", "children", "ctime", "mtime", "_value"];
+
+// Submit callback for ZooKeeper view: descend into the node, or show the value for leaf nodes
+fn zookeeper_on_submit(siv: &mut Cursive, parent: &str, row: view::QueryResultRow) {
+    let name = row.0[0].to_string();
+    let children = row.0[1].to_string();
+    let path = if parent == "/" {
+        format!("/{}", name)
+    } else {
+        format!("{}/{}", parent, name)
+    };
+
+    if children == "0" {
+        let value = row.0[4].to_string();
+        siv.add_layer(Dialog::info(value).title(path));
+        return;
+    }
+
+    show_zookeeper_node(siv, path);
+}
+
+// Only one node is shown at a time (on top of the root view), since the worker updates views by
+// name, so the view is replaced on navigation (Backspace - go to the parent)
+const ZOOKEEPER_NODE_VIEW: &str = "zookeeper_node";
+fn show_zookeeper_node(siv: &mut Cursive, path: String) {
+    let has_node_view = siv
+        .call_on_name(ZOOKEEPER_NODE_VIEW, |_: &mut view::QueryResultView| {})
+        .is_some();
+    if has_node_view {
+        siv.pop_layer();
+    }
+    // Root is the main view
+    if path == "/" {
+        return;
+    }
+
+    let context = siv.user_data::<ContextArc>().unwrap().clone();
+    let query = context
+        .lock()
+        .unwrap()
+        .clickhouse
+        .get_zookeeper_query(&path);
+    let mut view = view::QueryResultView::new(
+        context,
+        ZOOKEEPER_NODE_VIEW,
+        "name",
+        ZOOKEEPER_COLUMNS.to_vec(),
+        1,
+        query,
+    )
+    .unwrap_or_else(|_| panic!("Cannot get {}", ZOOKEEPER_NODE_VIEW));
+    view.set_default_sort("name", true).unwrap();
+    {
+        let path = path.clone();
+        view.set_on_submit(move |siv: &mut Cursive, row: view::QueryResultRow| {
+            zookeeper_on_submit(siv, &path, row);
+        });
+    }
+    let parent = match path.rsplit_once('/') {
+        Some(("", _)) | None => "/".to_string(),
+        Some((parent, _)) => parent.to_string(),
+    };
+    siv.add_layer(
+        OnEventView::new(
+            Dialog::around(
+                view.with_name(ZOOKEEPER_NODE_VIEW)
+                    // TODO: autocalculate
+                    .min_size((120, 20)),
+            )
+            .title(path),
+        )
+        .on_event(Key::Backspace, move |siv| {
+            show_zookeeper_node(siv, parent.clone());
+        }),
+    );
+}
+
+// Actions and layout of the QueryResultView (actions depends on the columns)
+fn show_query_result_view_impl<F>(
+    siv: &mut Cursive,
+    context: ContextArc,
+    name: &'static str,
+    mut view: view::QueryResultView,
+    columns: &[&str],
+    on_submit: Option<F>,
+) where
+    F: Fn(&mut Cursive, view::QueryResultRow) + Send + Sync + 'static,
+{
+    if let Some(on_submit) = on_submit {
+        view.set_on_submit(on_submit);
+    }
+
+    let mut event_view = OnEventView::new(view.with_name(name));
+    let context_copy = context.clone();
+    event_view.set_on_pre_event_inner(Event::Refresh, move |v, _| {
+        let action_callback = context_copy.lock().unwrap().pending_view_callback.take();
+        if let Some(action_callback) = action_callback {
+            let result = action_callback.as_ref()(v);
+            match result {
+                Err(err) => {
+                    return Some(EventResult::with_cb_once(move |siv: &mut Cursive| {
+                        siv.show_action_error(err);
+                    }));
+                }
+                Ok(event) => return event,
+            }
+        }
+        return Some(EventResult::Ignored);
+    });
+    // Mutating actions are not available in --read-only mode
+    let read_only = context.lock().unwrap().options.clickhouse.read_only;
+    {
+        let mut context = context.lock().unwrap();
+        context.begin_view_actions(name);
+        context.add_view_action(&mut event_view, "Copy row as JSON", 'y', copy_row_as_json);
+        context.add_view_action_without_shortcut(&mut event_view, "Export to file", export_rows);
+        if columns.contains(&"database") && columns.contains(&"table") {
+            context.add_view_action(
+                &mut event_view,
+                "Show merges/mutations for the table",
+                'm',
+                show_table_merges_and_mutations,
+            );
+            context.add_view_action(
+                &mut event_view,
+                "Show columns compression for the table",
+                'c',
+                show_table_columns_compression,
+            );
+            context.add_view_action_without_shortcut(
+                &mut event_view,
+                "Show filesystem cache for the table",
+                show_table_filesystem_cache,
+            );
+            context.add_view_action_without_shortcut(
+                &mut event_view,
+                "Show replicas of the table",
+                show_table_replicas,
+            );
+            context.add_view_action(
+                &mut event_view,
+                "Filter by database/table",
+                '/',
+                show_database_table_filter,
+            );
+            if !read_only {
+                context.add_view_action_without_shortcut(&mut event_view, "STOP MERGES", |v| {
+                    return execute_table_command(v, TableCommand::StopMerges);
+                });
+                context.add_view_action_without_shortcut(&mut event_view, "START MERGES", |v| {
+                    return execute_table_command(v, TableCommand::StartMerges);
+                });
+                context.add_view_action_without_shortcut(&mut event_view, "OPTIMIZE FINAL", |v| {
+                    return execute_table_command(v, TableCommand::OptimizeFinal);
+                });
+            }
+        }
+    }
+    if name == "system.merges" {
+        context.lock().unwrap().add_view_action(
+            &mut event_view,
+            "Show merge impact",
+            'i',
+            show_merge_impact,
+        );
+    }
+    if name == "system.dictionaries" && !read_only {
+        let mut context = context.lock().unwrap();
+        context.add_view_action(&mut event_view, "Reload dictionary", 'R', reload_dictionary);
+        context.add_view_action_without_shortcut(&mut event_view, "Reload all dictionaries", |v| {
+            let v = v
+                .downcast_mut::<NamedView<view::QueryResultView>>()
+                .unwrap()
+                .get_mut();
+            v.get_context()
+                .lock()
+                .unwrap()
+                .worker
+                .send(WorkerEvent::ReloadDictionary(None));
+            return Ok(Some(EventResult::consumed()));
+        });
+    }
+    if name == "system.detached_parts" && !read_only {
+        let mut context = context.lock().unwrap();
+        context.add_view_action(&mut event_view, "Attach detached part", 'a', |v| {
+            return alter_detached_part(v, true);
+        });
+        context.add_view_action(&mut event_view, "Drop detached part", 'D', |v| {
+            return alter_detached_part(v, false);
+        });
+    }
+    let view = event_view.full_screen();
+
+    siv.set_main_view(Dialog::around(view).title(name));
+    siv.focus_name(name).unwrap();
+}
+
+// Action for any QueryResultView
+fn copy_row_as_json(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    copy_to_clipboard(v.get_selected_row_json()?)?;
+    return Ok(Some(EventResult::consumed()));
+}
+
+// Action for any QueryResultView
+fn export_rows(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap()
+        .get_mut();
+    let (header, rows) = v.get_rows_json();
+    return Ok(Some(EventResult::Consumed(Some(Callback::from_fn(
+        move |siv: &mut Cursive| {
+            let header = header.clone();
+            let rows = rows.clone();
+            let save_cb = move |siv: &mut Cursive, path: &str| {
+                siv.pop_layer();
+                match save_rows(path, &header, &rows) {
+                    Ok(_) => {
+                        let message = format!("{} rows had been exported to {}", rows.len(), path);
+                        log::info!("{}", message);
+                        siv.add_layer(Dialog::info(message));
+                    }
+                    Err(err) => siv.add_layer(Dialog::info(err.to_string())),
+                }
+            };
+            let view = EditView::new()
+                .content("chdig.csv")
+                .on_submit(save_cb)
+                .min_width(40);
+            siv.add_layer(Dialog::around(view).title("Export to (.csv, .tsv or .json)"));
+        },
+    )))));
+}
+
+// "database.table" or "table" (substring match), to the WHERE expression
+fn get_database_table_filter(text: &str) -> String {
+    let (database, table) = match text.split_once('.') {
+        Some((database, table)) => (database, table),
+        None => ("", text),
+    };
+
+    let mut conditions = Vec::new();
+    if !database.is_empty() {
+        conditions.push(format!(
+            "position(database, '{}') > 0",
+            escape_string(database)
+        ));
+    }
+    if !table.is_empty() {
+        conditions.push(format!("position(table, '{}') > 0", escape_string(table)));
+    }
+    return conditions.join(" AND ");
+}
+
+fn show_database_table_filter(v: &mut dyn View) -> Result<Option<EventResult>> {
+    let v = v
+        .downcast_mut::<NamedView<view::QueryResultView>>()
+        .unwrap();
+    let view_name = v.name().to_string();
+    let cb_sink = v.get_mut().get_context().lock().unwrap().cb_sink.clone();
+
+    cb_sink
+        .send(Box::new(move |siv: &mut Cursive| {
+            let on_submit = move |siv: &mut Cursive, text: &str| {
+                let filter = get_database_table_filter(text.trim());
+                siv.pop_layer();
+                siv.call_on_name(&view_name, |v: &mut view::QueryResultView| {
+                    v.set_query_filter(filter);
+                });
+            };
+            siv.add_layer(
+                Dialog::new()
+                    .title("Filter (database.table or table, substring match)")
+                    .content(EditView::new().on_submit(on_submit).min_width(40)),
+            );
+        }))
+        .unwrap();
+
+    return Ok(Some(EventResult::consumed()));
+}
+
 impl Navigation for Cursive {
     fn has_view(&mut self, name: &str) -> bool {
         return self.focus_name(name).is_ok();
@@ -176,6 +894,7 @@ impl Navigation for Cursive {
     fn seek_time_frame(&mut self, is_sub: bool) {
         let mut context = self.user_data::<ContextArc>().unwrap().lock().unwrap();
         context.shift_time_interval(is_sub, 10);
+        save_time_frame(&context.options.view);
         context.trigger_view_refresh();
     }
 
@@ -208,6 +927,8 @@ impl Navigation for Cursive {
             let mut context = siv.user_data::<ContextArc>().unwrap().lock().unwrap();
             context.options.view.start = new_begin;
             context.options.view.end = new_end;
+            context.options.view.end_is_now = false;
+            save_time_frame(&context.options.view);
             context.trigger_view_refresh();
         };
 
@@ -237,10 +958,8 @@ impl Navigation for Cursive {
         let theme = self.make_theme_from_therminal();
         self.set_theme(theme);
 
-        self.statusbar(format!(
-            "Connected to {}.",
-            context.lock().unwrap().server_version
-        ));
+        let status = context.lock().unwrap().connection_status();
+        self.statusbar(status);
 
         self.add_layer(
             LinearLayout::horizontal()
@@ -270,6 +989,11 @@ impl Navigation for Cursive {
             ChDigViews::LastQueries => self.show_clickhouse_last_query_log(context.clone()),
             ChDigViews::SlowQueries => self.show_clickhouse_slow_query_log(context.clone()),
             ChDigViews::Merges => self.show_clickhouse_merges(context.clone()),
+            ChDigViews::Parts => self.show_clickhouse_parts(context.clone()),
+            ChDigViews::DetachedParts => self.show_clickhouse_detached_parts(context.clone()),
+            ChDigViews::Disks => self.show_clickhouse_disks(context.clone()),
+            ChDigViews::ZooKeeper => self.show_clickhouse_zookeeper(context.clone()),
+            ChDigViews::PartLog => self.show_clickhouse_part_log(context.clone()),
             ChDigViews::Mutations => self.show_clickhouse_mutations(context.clone()),
             ChDigViews::ReplicationQueue => self.show_clickhouse_replication_queue(context.clone()),
             ChDigViews::ReplicatedFetches => {
@@ -280,6 +1004,9 @@ impl Navigation for Cursive {
             ChDigViews::Backups => self.show_clickhouse_backups(context.clone()),
             ChDigViews::Dictionaries => self.show_clickhouse_dictionaries(context.clone()),
             ChDigViews::ServerLogs => self.show_clickhouse_server_logs(context.clone()),
+            ChDigViews::Dashboard => self.show_clickhouse_dashboard(context.clone()),
+            // SummaryView is always there (and it is refreshed with --delay-interval)
+            ChDigViews::Summary => {}
         }
     }
 
@@ -304,6 +1031,28 @@ impl Navigation for Cursive {
             |siv| siv.show_server_flamegraph(false),
         );
 
+        context.add_global_action_without_shortcut(self, "Merges/mutations timeline", |siv| {
+            siv.show_merges_timeline()
+        });
+
+        context.add_global_action_without_shortcut(self, "Check permissions", |siv| {
+            siv.user_data::<ContextArc>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .worker
+                .send(WorkerEvent::CheckPermissions);
+        });
+        context.add_global_action_without_shortcut(self, "Switch connection", |siv| {
+            siv.show_connections()
+        });
+        context.add_global_action_without_shortcut(self, "Save summary baseline", |siv| {
+            siv.save_summary_baseline()
+        });
+        context.add_global_action_without_shortcut(self, "Compare summary with baseline", |siv| {
+            siv.show_summary_baselines()
+        });
+
         context.add_global_action(
             self,
             "chdig debug console",
@@ -327,12 +1076,36 @@ impl Navigation for Cursive {
         context.add_global_action(self, "Set time interval", Event::AltChar('t'), |siv| {
             siv.select_time_frame()
         });
+        context.add_global_action(self, "Global search", Event::CtrlChar('f'), |siv| {
+            siv.show_global_search()
+        });
+        // Custom query is executed periodically, so it is not available in --read-only mode
+        // (even though it is executed with readonly=1)
+        if !context.options.clickhouse.read_only {
+            context.add_global_action_without_shortcut(self, "Custom query view", |siv| {
+                siv.show_custom_query_view_dialog()
+            });
+        }
+        context.add_global_action_without_shortcut(self, "Threads breakdown", |siv| {
+            siv.user_data::<ContextArc>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .worker
+                .send(WorkerEvent::ShowThreadsBreakdown);
+        });
     }
 
     fn initialize_views_menu(&mut self, context: ContextArc) {
         let mut c = context.lock().unwrap();
 
         // TODO: macro
+        {
+            let ctx = context.clone();
+            c.add_view("Dashboard", move |siv| {
+                siv.show_clickhouse_dashboard(ctx.clone())
+            });
+        }
         {
             let ctx = context.clone();
             c.add_view("Processes", move |siv| {
@@ -356,6 +1129,32 @@ impl Navigation for Cursive {
             let ctx = context.clone();
             c.add_view("Merges", move |siv| siv.show_clickhouse_merges(ctx.clone()));
         }
+        {
+            let ctx = context.clone();
+            c.add_view("Parts", move |siv| siv.show_clickhouse_parts(ctx.clone()));
+        }
+        {
+            let ctx = context.clone();
+            c.add_view("Detached parts", move |siv| {
+                siv.show_clickhouse_detached_parts(ctx.clone())
+            });
+        }
+        {
+            let ctx = context.clone();
+            c.add_view("Part log", move |siv| {
+                siv.show_clickhouse_part_log(ctx.clone())
+            });
+        }
+        {
+            let ctx = context.clone();
+            c.add_view("Disks", move |siv| siv.show_clickhouse_disks(ctx.clone()));
+        }
+        {
+            let ctx = context.clone();
+            c.add_view("ZooKeeper", move |siv| {
+                siv.show_clickhouse_zookeeper(ctx.clone())
+            });
+        }
         {
             let ctx = context.clone();
             c.add_view("Mutations", move |siv| {
@@ -426,7 +1225,7 @@ impl Navigation for Cursive {
             }
 
             text.append_styled("\nActions:\n\n", Effect::Bold);
-            for shortcut in context.view_actions.iter() {
+            for shortcut in context.view_actions.actions.iter() {
                 text.append(shortcut.description.preview_styled());
             }
         }
@@ -534,6 +1333,7 @@ impl Navigation for Cursive {
                             let mut context = context.lock().unwrap();
                             let action_callback = context
                                 .view_actions
+                                .actions
                                 .iter()
                                 .find(|x| x.description.text == selected_action)
                                 .unwrap()
@@ -554,10 +1354,10 @@ impl Navigation for Cursive {
                 {
                     let context = context.clone();
                     let context = context.lock().unwrap();
-                    for action in context.view_actions.iter() {
+                    for action in context.view_actions.actions.iter() {
                         select.add_item_str(action.description.text);
                     }
-                    if context.view_actions.is_empty() {
+                    if context.view_actions.actions.is_empty() {
                         return;
                     }
                 }
@@ -596,7 +1396,7 @@ impl Navigation for Cursive {
                 .global_actions
                 .iter()
                 .map(|x| &x.description)
-                .chain(context.view_actions.iter().map(|x| &x.description))
+                .chain(context.view_actions.actions.iter().map(|x| &x.description))
                 .cloned()
                 .collect();
         }
@@ -629,6 +1429,7 @@ impl Navigation for Cursive {
                 let mut context = context.lock().unwrap();
                 if let Some(action) = context
                     .view_actions
+                    .actions
                     .iter()
                     .find(|x| x.description.text == action_text)
                 {
@@ -658,11 +1459,190 @@ impl Navigation for Cursive {
         ));
     }
 
+    fn show_merges_timeline(&mut self) {
+        let mut context = self.user_data::<ContextArc>().unwrap().lock().unwrap();
+        let start = context.options.view.start;
+        let end = context.options.view.end;
+        context
+            .worker
+            .send(WorkerEvent::ShowMergesTimeline(start, end));
+    }
+
+    fn show_global_search(&mut self) {
+        let on_submit = move |siv: &mut Cursive, text: &str| {
+            let context = siv.user_data::<ContextArc>().unwrap().clone();
+            siv.pop_layer();
+            siv.global_search(context, text.trim());
+        };
+        self.add_layer(
+            Dialog::new()
+                .title("Search (query_id, part name, database.table)")
+                .content(EditView::new().on_submit(on_submit).min_width(40)),
+        );
+    }
+
+    fn show_connections(&mut self) {
+        let connections = get_connections();
+        if connections.is_empty() {
+            self.add_layer(Dialog::info(
+                "No connections_credentials in the clickhouse-client config",
+            ));
+            return;
+        }
+
+        let mut select = SelectView::new().autojump();
+        select.add_all_str(connections);
+        select.set_on_submit(|siv, connection: &str| {
+            siv.pop_layer();
+            siv.user_data::<ContextArc>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .worker
+                .send(WorkerEvent::SwitchConnection(connection.to_string()));
+        });
+        self.add_layer(Dialog::around(select.scrollable()).title("Switch connection"));
+    }
+
+    fn save_summary_baseline(&mut self) {
+        let on_submit = |siv: &mut Cursive, name: &str| {
+            siv.pop_layer();
+            let metrics = siv
+                .call_on_name("summary", |v: &mut view::SummaryView| v.get_metrics())
+                .flatten();
+            let Some(metrics) = metrics else {
+                siv.add_layer(Dialog::info("Summary is not loaded yet"));
+                return;
+            };
+            match save_summary_baseline(name, &metrics) {
+                Ok(_) => siv.add_layer(Dialog::info(format!("Baseline '{}' saved", name))),
+                Err(err) => siv.show_action_error(err),
+            }
+        };
+        self.add_layer(
+            Dialog::new()
+                .title("Save summary baseline (name)")
+                .content(EditView::new().on_submit(on_submit).min_width(30)),
+        );
+    }
+
+    fn show_summary_baselines(&mut self) {
+        let baselines = get_summary_baselines();
+        if baselines.is_empty() {
+            self.add_layer(Dialog::info(
+                "No summary baselines (see \"Save summary baseline\" action)",
+            ));
+            return;
+        }
+
+        let mut select = SelectView::new().autojump();
+        select.add_all_str(baselines);
+        select.set_on_submit(|siv, name: &str| {
+            siv.pop_layer();
+            let baseline = match read_summary_baseline(name) {
+                Ok(baseline) => baseline,
+                Err(err) => return siv.show_action_error(err),
+            };
+            let metrics = siv
+                .call_on_name("summary", |v: &mut view::SummaryView| v.get_metrics())
+                .flatten();
+            let Some(metrics) = metrics else {
+                siv.add_layer(Dialog::info("Summary is not loaded yet"));
+                return;
+            };
+
+            let fmt_bytes = SizeFormatter::new()
+                .with_base(Base::Base2)
+                .with_style(SizeStyle::Abbreviated);
+            let fmt = |metric: &str, value: u64| {
+                if metric.ends_with("_bytes") {
+                    return fmt_bytes.format(value as i64);
+                }
+                return value.to_string();
+            };
+
+            let mut content = StyledString::new();
+            content.append_styled(
+                format!(
+                    "{:<26} {:>12} {:>12} {:>13}\n",
+                    "metric", "baseline", "current", "delta"
+                ),
+                Effect::Bold,
+            );
+            for (metric, current) in metrics {
+                let Some(&base) = baseline.get(metric) else {
+                    continue;
+                };
+                let delta = if current >= base {
+                    format!("+{}", fmt(metric, current - base))
+                } else {
+                    format!("-{}", fmt(metric, base - current))
+                };
+                content.append_plain(format!(
+                    "{:<26} {:>12} {:>12} ",
+                    metric,
+                    fmt(metric, base),
+                    fmt(metric, current)
+                ));
+                let color = match current.cmp(&base) {
+                    std::cmp::Ordering::Greater => BaseColor::Red.light(),
+                    std::cmp::Ordering::Less => BaseColor::Green.light(),
+                    std::cmp::Ordering::Equal => BaseColor::White.light(),
+                };
+                content.append_styled(format!("{:>13}\n", delta), color);
+            }
+            siv.add_layer(
+                Dialog::around(TextView::new(content).scrollable())
+                    .title(format!("Summary compared with baseline '{}'", name))
+                    .dismiss_button("Close"),
+            );
+        });
+        self.add_layer(Dialog::around(select.scrollable()).title("Summary baselines"));
+    }
+
+    fn global_search(&mut self, context: ContextArc, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let target = get_global_search_target(text);
+        log::debug!("Global search for '{}' ({:?})", text, target);
+        match target {
+            GlobalSearchTarget::Part | GlobalSearchTarget::Table => {
+                // Only table name is matched, since database and table are different columns
+                let filter = match target {
+                    GlobalSearchTarget::Table => text.split_once('.').unwrap().1,
+                    _ => text,
+                };
+                self.show_clickhouse_merges(context);
+                self.call_on_name("system.merges", |v: &mut view::QueryResultView| {
+                    v.set_filter(filter);
+                });
+            }
+            // query_id and the fallback are handled by the queries view filter (it matches
+            // query_id as well as query, user, ...)
+            GlobalSearchTarget::QueryId | GlobalSearchTarget::Unknown => {
+                self.show_clickhouse_processes(context);
+                self.call_on_name("processes", |v: &mut OnEventView<view::ProcessesView>| {
+                    v.get_inner_mut().set_filter(text);
+                });
+            }
+        }
+    }
+
     fn drop_main_view(&mut self) {
         while self.screen_mut().len() > 2 {
             self.pop_layer();
         }
 
+        // Actions of QueryResultView are registered by the navigation (since they depends on the
+        // table), so they are removed here, while ProcessesView cleans them on drop.
+        self.user_data::<ContextArc>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .clear_view_actions();
+
         self.call_on_name("main", |main_view: &mut LinearLayout| {
             // Views that should not be touched:
             // - menu text
@@ -695,7 +1675,9 @@ impl Navigation for Cursive {
                                     .with_name("main_status"),
                             )
                             .child(DummyView.fixed_width(1))
-                            .child(TextView::new("").with_name("status")),
+                            .child(TextView::new("").with_name("status"))
+                            .child(DummyView.full_width())
+                            .child(TextView::new("").with_name("clock")),
                     )
                     .full_width(),
                 ),
@@ -712,7 +1694,37 @@ impl Navigation for Cursive {
         self.call_on_name("status", |text_view: &mut TextView| {
             text_view.set_content(content);
         })
-        .expect("set_status")
+        .expect("set_status");
+
+        // Refresh the clock along with the status (it is updated on each processed event)
+        let clock = self
+            .user_data::<ContextArc>()
+            .map(|context| get_clock_text(&context.lock().unwrap()));
+        if let Some(clock) = clock {
+            self.call_on_name("clock", |text_view: &mut TextView| {
+                text_view.set_content(clock);
+            });
+        }
+    }
+
+    fn show_action_error(&mut self, err: anyhow::Error) {
+        let quiet_errors = self
+            .user_data::<ContextArc>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .options
+            .view
+            .quiet_errors;
+        if quiet_errors {
+            log::error!("{}", err);
+            self.set_statusbar_content(StyledString::styled(
+                format!("Error: {}", err),
+                BaseColor::Red.dark(),
+            ));
+        } else {
+            self.add_layer(Dialog::info(err.to_string()));
+        }
     }
 
     fn show_clickhouse_processes(&mut self, context: ContextArc) {
@@ -720,6 +1732,7 @@ impl Navigation for Cursive {
             return;
         }
 
+        let no_subqueries = context.lock().unwrap().options.view.no_subqueries;
         self.drop_main_view();
         self.set_main_view(
             Dialog::around(
@@ -731,7 +1744,8 @@ impl Navigation for Cursive {
                 .with_name("processes")
                 .full_screen(),
             )
-            .title("Queries"),
+            .title(view::ProcessesType::ProcessList.title(no_subqueries))
+            .with_name("processes_dialog"),
         );
         self.focus_name("processes").unwrap();
     }
@@ -741,6 +1755,7 @@ impl Navigation for Cursive {
             return;
         }
 
+        let no_subqueries = context.lock().unwrap().options.view.no_subqueries;
         self.drop_main_view();
         self.set_main_view(
             Dialog::around(
@@ -752,7 +1767,8 @@ impl Navigation for Cursive {
                 .with_name("slow_query_log")
                 .full_screen(),
             )
-            .title("Slow queries"),
+            .title(view::ProcessesType::SlowQueryLog.title(no_subqueries))
+            .with_name("slow_query_log_dialog"),
         );
         self.focus_name("slow_query_log").unwrap();
     }
@@ -762,6 +1778,7 @@ impl Navigation for Cursive {
             return;
         }
 
+        let no_subqueries = context.lock().unwrap().options.view.no_subqueries;
         self.drop_main_view();
         self.set_main_view(
             Dialog::around(
@@ -773,7 +1790,8 @@ impl Navigation for Cursive {
                 .with_name("last_query_log")
                 .full_screen(),
             )
-            .title("Last queries"),
+            .title(view::ProcessesType::LastQueryLog.title(no_subqueries))
+            .with_name("last_query_log_dialog"),
         );
         self.focus_name("last_query_log").unwrap();
     }
@@ -807,6 +1825,169 @@ impl Navigation for Cursive {
         );
     }
 
+    fn show_clickhouse_parts(&mut self, context: ContextArc) {
+        let table = "system.parts";
+        let mut columns = vec![
+            "database",
+            "table",
+            "name",
+            "partition_id partition",
+            "rows",
+            "bytes_on_disk size",
+            "toFloat64(if(data_compressed_bytes > 0, round(data_uncompressed_bytes / data_compressed_bytes, 2), 0)) ratio",
+            "level",
+        ];
+
+        self.show_query_result_view(
+            context,
+            table,
+            Some("active"),
+            "size",
+            &mut columns,
+            3,
+            Some(|siv: &mut Cursive, row: view::QueryResultRow| {
+                let context = siv.user_data::<ContextArc>().unwrap().clone();
+                // host column is prepended in cluster mode
+                let offset = if context.lock().unwrap().options.clickhouse.cluster.is_some() {
+                    1
+                } else {
+                    0
+                };
+                let database = row.0[offset].to_string();
+                let table = row.0[offset + 1].to_string();
+                let part = row.0[offset + 2].to_string();
+                show_part_log(siv, context, database, table, part);
+            }),
+            &HashMap::new(),
+        );
+    }
+
+    fn show_clickhouse_detached_parts(&mut self, context: ContextArc) {
+        let table = "system.detached_parts";
+        let mut columns = vec![
+            "database",
+            "table",
+            "name",
+            "partition_id partition",
+            "reason",
+            "disk",
+        ];
+
+        // ATTACH/DROP actions are registered in show_query_result_view_for_query()
+        self.show_query_result_view(
+            context,
+            table,
+            None,
+            "name",
+            &mut columns,
+            3,
+            QUERY_RESULT_VIEW_NOP_CALLBACK,
+            &HashMap::new(),
+        );
+    }
+
+    fn show_clickhouse_part_log(&mut self, context: ContextArc) {
+        let name = "part_log_timeline";
+        if self.has_view(name) {
+            return;
+        }
+
+        let (query, cluster) = {
+            let context = context.lock().unwrap();
+            (
+                context.clickhouse.get_part_log_timeline_query(
+                    context.options.view.start,
+                    context.options.view.end,
+                ),
+                context.options.clickhouse.cluster.is_some(),
+            )
+        };
+        let query = match query {
+            Ok(query) => query,
+            Err(err) => {
+                self.add_layer(Dialog::info(err.to_string()));
+                return;
+            }
+        };
+        let mut columns = vec![
+            "event_time",
+            "type",
+            "database",
+            "table",
+            "part",
+            "rows",
+            "size",
+            "duration_ms",
+            "error",
+            "exception",
+        ];
+        let mut columns_to_compare = 5;
+        if cluster {
+            columns.insert(0, "host");
+            columns_to_compare += 1;
+        }
+
+        self.show_query_result_view_for_query(
+            context,
+            name,
+            "event_time",
+            columns,
+            columns_to_compare,
+            QUERY_RESULT_VIEW_NOP_CALLBACK,
+            query,
+        );
+    }
+
+    fn show_clickhouse_disks(&mut self, context: ContextArc) {
+        let table = "system.disks";
+        let mut columns = vec![
+            "name",
+            "path",
+            "free_space free",
+            "total_space total",
+            "unreserved_space unreserved",
+            "toFloat64(if(total_space > 0, round((total_space - free_space) / total_space * 100, 2), 0)) used_percent",
+        ];
+
+        self.show_query_result_view(
+            context,
+            table,
+            None,
+            "free",
+            &mut columns,
+            1,
+            QUERY_RESULT_VIEW_NOP_CALLBACK,
+            &HashMap::new(),
+        );
+        // The disk with the least free space goes first
+        self.call_on_name_or_render_error(table, |v: &mut view::QueryResultView| {
+            return v.set_default_sort("free", true);
+        });
+    }
+
+    fn show_clickhouse_zookeeper(&mut self, context: ContextArc) {
+        let name = "zookeeper";
+        if self.has_view(name) {
+            return;
+        }
+
+        let query = context.lock().unwrap().clickhouse.get_zookeeper_query("/");
+        self.show_query_result_view_for_query(
+            context,
+            name,
+            "name",
+            ZOOKEEPER_COLUMNS.to_vec(),
+            1,
+            Some(|siv: &mut Cursive, row: view::QueryResultRow| {
+                zookeeper_on_submit(siv, "/", row);
+            }),
+            query,
+        );
+        self.call_on_name_or_render_error(name, |v: &mut view::QueryResultView| {
+            return v.set_default_sort("name", true);
+        });
+    }
+
     fn show_clickhouse_mutations(&mut self, context: ContextArc) {
         let table = "system.mutations";
         let mut columns = vec![
@@ -970,6 +2151,7 @@ impl Navigation for Cursive {
     fn show_clickhouse_dictionaries(&mut self, context: ContextArc) {
         let table = "system.dictionaries";
         let mut columns = vec![
+            "database",
             "name",
             "status::String status",
             "origin",
@@ -982,13 +2164,14 @@ impl Navigation for Cursive {
             "last_exception",
         ];
 
+        // Reload actions are registered in show_query_result_view_for_query()
         self.show_query_result_view(
             context,
             table,
             None,
             "memory",
             &mut columns,
-            1,
+            2,
             QUERY_RESULT_VIEW_NOP_CALLBACK,
             &HashMap::new(),
         );
@@ -1021,6 +2204,124 @@ impl Navigation for Cursive {
         self.focus_name("server_logs").unwrap();
     }
 
+    fn show_clickhouse_dashboard(&mut self, context: ContextArc) {
+        let name = "dashboard";
+        if self.has_view(name) {
+            return;
+        }
+
+        let columns = vec!["signal", "value", "status", "severity"];
+        let query = context.lock().unwrap().clickhouse.get_dashboard_query();
+
+        self.show_query_result_view_for_query(
+            context,
+            name,
+            "severity",
+            columns,
+            1,
+            Some(|siv: &mut Cursive, row: view::QueryResultRow| {
+                let context = siv.user_data::<ContextArc>().unwrap().clone();
+                let signal = row.0[0].to_string();
+                match signal.as_str() {
+                    "Replication lag" => siv.show_clickhouse_replicas(context),
+                    "Failed mutations" => siv.show_clickhouse_mutations(context),
+                    "Stuck merges" => siv.show_clickhouse_merges(context),
+                    "Recent errors" => siv.show_clickhouse_errors(context),
+                    "Memory usage" => siv.show_clickhouse_processes(context),
+                    _ => log::warn!("No view for {}", signal),
+                }
+            }),
+        );
+    }
+
+    fn show_custom_query_view_dialog(&mut self) {
+        let on_submit = move |siv: &mut Cursive| {
+            let query = siv
+                .call_on_name("custom_query", |view: &mut EditView| view.get_content())
+                .unwrap();
+            let sort_by = siv
+                .call_on_name("custom_query_sort_by", |view: &mut EditView| {
+                    view.get_content()
+                })
+                .unwrap();
+
+            siv.pop_layer();
+
+            let query = query.trim().trim_end_matches(';').to_string();
+            if query.is_empty() {
+                return;
+            }
+            log::debug!("Custom query view for '{}' (sort by: '{}')", query, sort_by);
+            siv.user_data::<ContextArc>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .worker
+                .send(WorkerEvent::CustomQueryView(
+                    query,
+                    sort_by.trim().to_string(),
+                ));
+        };
+
+        self.add_layer(
+            Dialog::new()
+                .title("Custom query view")
+                .content(
+                    LinearLayout::vertical()
+                        .child(TextView::new("SELECT query (executed with readonly=1):"))
+                        .child(EditView::new().with_name("custom_query").min_width(60))
+                        .child(DummyView)
+                        .child(TextView::new(
+                            "sort by (column name, first column if empty):",
+                        ))
+                        .child(EditView::new().with_name("custom_query_sort_by")),
+                )
+                .button("Submit", on_submit),
+        );
+    }
+
+    fn show_custom_query_view(
+        &mut self,
+        context: ContextArc,
+        query: String,
+        columns: Vec<String>,
+        sort_by: String,
+    ) {
+        let name = "custom_query_view";
+        let sort_by = if sort_by.is_empty() {
+            columns.first().cloned().unwrap_or_default()
+        } else {
+            sort_by
+        };
+
+        self.drop_main_view();
+
+        // Names of the columns are taken from the block, so they should be used as is
+        let view = match view::QueryResultView::new_with_column_names(
+            context.clone(),
+            name,
+            &sort_by,
+            columns.clone(),
+            columns.len(),
+            query,
+        ) {
+            Ok(view) => view,
+            Err(err) => {
+                self.add_layer(Dialog::info(err.to_string()));
+                return;
+            }
+        };
+        let columns = columns.iter().map(String::as_str).collect::<Vec<_>>();
+        show_query_result_view_impl(
+            self,
+            context,
+            name,
+            view,
+            &columns,
+            QUERY_RESULT_VIEW_NOP_CALLBACK,
+        );
+    }
+
     fn show_query_result_view<F>(
         &mut self,
         context: ContextArc,
@@ -1065,24 +2366,46 @@ impl Navigation for Cursive {
             settings,
         );
 
+        self.show_query_result_view_for_query(
+            context,
+            table,
+            sort_by,
+            columns.clone(),
+            columns_to_compare,
+            on_submit,
+            query,
+        );
+    }
+
+    fn show_query_result_view_for_query<F>(
+        &mut self,
+        context: ContextArc,
+        name: &'static str,
+        sort_by: &str,
+        columns: Vec<&str>,
+        columns_to_compare: usize,
+        on_submit: Option<F>,
+        query: String,
+    ) where
+        F: Fn(&mut Cursive, view::QueryResultRow) + Send + Sync + 'static,
+    {
         self.drop_main_view();
 
-        let mut view = view::QueryResultView::new(
+        let view = match view::QueryResultView::new(
             context.clone(),
-            table,
+            name,
             sort_by,
             columns.clone(),
             columns_to_compare,
             query,
-        )
-        .unwrap_or_else(|_| panic!("Cannot get {}", table));
-        if let Some(on_submit) = on_submit {
-            view.set_on_submit(on_submit);
-        }
-        let view = view.with_name(table).full_screen();
-
-        self.set_main_view(Dialog::around(view).title(table));
-        self.focus_name(table).unwrap();
+        ) {
+            Ok(view) => view,
+            Err(err) => {
+                self.add_layer(Dialog::info(err.to_string()));
+                return;
+            }
+        };
+        show_query_result_view_impl(self, context, name, view, &columns, on_submit);
     }
 
     fn call_on_name_or_render_error<V, F>(&mut self, name: &str, callback: F)
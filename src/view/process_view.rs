@@ -1,6 +1,10 @@
-use crate::interpreter::QueryProcess;
+use crate::interpreter::{ContextArc, QueryProcess};
 use crate::view::{ExtTableView, TableViewItem};
-use cursive::{view::ViewWrapper, wrap_impl};
+use cursive::{
+    event::{Event, EventResult},
+    view::{View, ViewWrapper},
+    wrap_impl,
+};
 use humantime::format_duration;
 use size::{Base, SizeFormatter, Style};
 use std::cmp::Ordering;
@@ -102,11 +106,12 @@ impl TableViewItem<QueryProcessDetailsColumn> for QueryProcessDetails {
 }
 
 pub struct ProcessView {
+    context: ContextArc,
     table: ExtTableView<QueryProcessDetails, QueryProcessDetailsColumn>,
 }
 
 impl ProcessView {
-    pub fn new(query_process: QueryProcess) -> Self {
+    pub fn new(context: ContextArc, query_process: QueryProcess) -> Self {
         let mut table = ExtTableView::<QueryProcessDetails, QueryProcessDetailsColumn>::default();
         let inner_table = table.get_inner_mut().get_inner_mut();
         inner_table.add_column(QueryProcessDetailsColumn::Name, "Name", |c| c.width(30));
@@ -127,13 +132,36 @@ impl ProcessView {
         }
         inner_table.set_items(items);
 
-        inner_table.sort_by(QueryProcessDetailsColumn::Current, Ordering::Greater);
-        inner_table.set_selected_row(0);
+        let mut view = ProcessView { context, table };
+        view.update_sort();
+        return view;
+    }
 
-        return ProcessView { table };
+    fn update_sort(&mut self) {
+        let sort_by_name = self.context.lock().unwrap().profile_events_sort_by_name;
+        let inner_table = self.table.get_inner_mut().get_inner_mut();
+        if sort_by_name {
+            inner_table.sort_by(QueryProcessDetailsColumn::Name, Ordering::Less);
+        } else {
+            inner_table.sort_by(QueryProcessDetailsColumn::Current, Ordering::Greater);
+        }
+        inner_table.set_selected_row(0);
     }
 }
 
 impl ViewWrapper for ProcessView {
     wrap_impl!(self.table: ExtTableView<QueryProcessDetails, QueryProcessDetailsColumn>);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        // Toggle sort by name/value (remembered for the session)
+        if event == Event::Char('o') {
+            {
+                let mut context = self.context.lock().unwrap();
+                context.profile_events_sort_by_name = !context.profile_events_sort_by_name;
+            }
+            self.update_sort();
+            return EventResult::consumed();
+        }
+        return self.table.on_event(event);
+    }
 }
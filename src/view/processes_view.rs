@@ -2,27 +2,39 @@ use anyhow::{Error, Result};
 use chrono::{DateTime, Local};
 use chrono_tz::Tz;
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::mem::take;
 use std::sync::{Arc, Mutex};
 
-use cursive::traits::{Nameable, Resizable};
+use cursive::traits::{Nameable, Resizable, Scrollable};
 use cursive::{
-    event::{Callback, Event, EventResult},
+    event::{Callback, Event, EventResult, Key},
     inner_getters,
     view::ViewWrapper,
     views::{self, Dialog, EditView, OnEventView},
     Cursive,
 };
+use cursive_table_view::TableView;
 use size::{Base, SizeFormatter, Style};
 
 use crate::interpreter::{
-    clickhouse::Columns, clickhouse::TraceType, options::ViewOptions, BackgroundRunner, ContextArc,
-    QueryProcess, WorkerEvent,
+    clickhouse::Columns, clickhouse::TraceType, options::save_time_frame, options::DurationFormat,
+    options::ViewOptions, options::QUERIES_COLUMNS, BackgroundRunner, ContextArc, QueryProcess,
+    WorkerEvent,
+};
+use crate::view::{
+    ExtTableView, LogView, Navigation, ProcessView, QueryResultView, SummaryView, TableViewItem,
+    TextLogView,
 };
-use crate::view::{ExtTableView, ProcessView, QueryResultView, TableViewItem, TextLogView};
 use crate::wrap_impl_no_move;
-use chdig::{edit_query, get_query};
+use chdig::{
+    copy_to_clipboard, edit_query, get_query, get_query_with_set_statements, highlight_sql,
+    save_json,
+};
+
+// Extra time around the query for "Set time frame to the query" (to include events that had been
+// logged slightly before/after the query, i.e. due to flushing)
+const QUERY_TIME_DRIFT_BUFFER_SECONDS: i64 = 1;
 
 // Analog of mapFromArrays() in ClickHouse
 fn map_from_arrays<K, V>(keys: Vec<K>, values: Vec<V>) -> HashMap<K, V>
@@ -83,6 +95,578 @@ fn queries_sum_profile_events(queries: &mut HashMap<String, QueryProcess>) {
     }
 }
 
+// Number of refreshes to keep for "Show rows rate chart"
+const ROWS_RATE_CHART_SIZE: usize = 60;
+
+// History of read/written rows rate of the query (updated on each refresh)
+struct RowsRateChart {
+    query_id: String,
+    // To detect restart of the query (the same query_id)
+    elapsed: f64,
+    read: VecDeque<f64>,
+    written: VecDeque<f64>,
+}
+impl RowsRateChart {
+    fn new(query_id: String) -> Self {
+        return RowsRateChart {
+            query_id,
+            elapsed: 0.,
+            read: VecDeque::new(),
+            written: VecDeque::new(),
+        };
+    }
+
+    fn update(&mut self, query: &QueryProcess) {
+        if query.elapsed < self.elapsed {
+            self.read.clear();
+            self.written.clear();
+        }
+        self.elapsed = query.elapsed;
+        // Rate is not known until the second refresh
+        if query.prev_elapsed.is_none() {
+            return;
+        }
+        for (values, value) in [
+            (&mut self.read, query.read_rows_rate()),
+            (&mut self.written, query.written_rows_rate()),
+        ] {
+            if values.len() == ROWS_RATE_CHART_SIZE {
+                values.pop_front();
+            }
+            values.push_back(value);
+        }
+    }
+
+    fn render(&self) -> String {
+        let fmt_rows = SizeFormatter::new()
+            .with_base(Base::Base10)
+            .with_style(Style::Abbreviated);
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let sparkline = |values: &VecDeque<f64>| {
+            let max = values.iter().cloned().fold(0., f64::max);
+            return values
+                .iter()
+                .map(|&v| {
+                    if max <= 0. {
+                        return BARS[0];
+                    }
+                    return BARS[((v / max * (BARS.len() - 1) as f64).round()) as usize];
+                })
+                .collect::<String>();
+        };
+        let last = |values: &VecDeque<f64>| fmt_rows.format(*values.back().unwrap_or(&0.) as i64);
+
+        return format!(
+            "Query id: {}\n\nread:    {} {}/s\nwritten: {} {}/s",
+            self.query_id,
+            sparkline(&self.read),
+            last(&self.read),
+            sparkline(&self.written),
+            last(&self.written),
+        );
+    }
+}
+
+// Width of the column from --column-widths (by the name in the header) or the default
+fn column_width(options: &ViewOptions, column: &str, default: usize) -> usize {
+    return options
+        .column_widths
+        .as_ref()
+        .and_then(|widths| widths.get(column).copied())
+        .unwrap_or(default);
+}
+
+// Column and its default width (None - the rest of the space) by the name from the header
+fn query_processes_column(
+    options: &ViewOptions,
+    name: &str,
+) -> Option<(QueryProcessesColumn, Option<usize>)> {
+    // UUID (36) + "-> " for initial queries with subqueries
+    let query_id_width = if options.full_query_id { 39 } else { 12 };
+    let (column, width) = match name {
+        "query_id" => (QueryProcessesColumn::QueryId, Some(query_id_width)),
+        "cpu" => (QueryProcessesColumn::Cpu, Some(8)),
+        "io_wait" => (QueryProcessesColumn::IOWait, Some(11)),
+        "cpu_wait" => (QueryProcessesColumn::CPUWait, Some(12)),
+        "user" => (QueryProcessesColumn::User, Some(8)),
+        "client" => (QueryProcessesColumn::ClientAddress, Some(15)),
+        "thr" => (QueryProcessesColumn::Threads, Some(6)),
+        "mem" => (QueryProcessesColumn::Memory, Some(6)),
+        "disk" => (QueryProcessesColumn::DiskIO, Some(7)),
+        "io" => (QueryProcessesColumn::IO, Some(7)),
+        "net" => (QueryProcessesColumn::NetIO, Some(6)),
+        "read_rows" => (QueryProcessesColumn::ReadRows, Some(11)),
+        "read_bytes" => (QueryProcessesColumn::ReadBytes, Some(12)),
+        "progress" => (QueryProcessesColumn::Progress, Some(13)),
+        "cache" => (QueryProcessesColumn::CacheHitRatio, Some(8)),
+        "killed" => (QueryProcessesColumn::Killed, Some(8)),
+        "elapsed" => (QueryProcessesColumn::Elapsed, Some(11)),
+        "query" => (QueryProcessesColumn::Query, None),
+        _ => return None,
+    };
+    let width = options
+        .column_widths
+        .as_ref()
+        .and_then(|widths| widths.get(name).copied())
+        .or(width);
+    return Some((column, width));
+}
+
+// &'static str for the name of the column from QUERIES_COLUMNS (validated by --columns)
+fn query_processes_column_name(name: &str) -> Option<&'static str> {
+    return QUERIES_COLUMNS.iter().find(|c| **c == name).copied();
+}
+
+// Description of the column and where it is derived from (for the columns legend)
+fn query_processes_column_description(name: &str) -> (&'static str, &'static str) {
+    return match name {
+        "query_id" => (
+            "query id (\"-> \" for initial queries with subqueries)",
+            "query_id",
+        ),
+        "cpu" => (
+            "CPU usage (100 % - one core)",
+            "OSCPUVirtualTimeMicroseconds",
+        ),
+        "io_wait" => (
+            "time waiting for IO (100 % - one thread)",
+            "OSIOWaitMicroseconds",
+        ),
+        "cpu_wait" => (
+            "time waiting for CPU, i.e. the server is overloaded (100 % - one thread)",
+            "OSCPUWaitMicroseconds",
+        ),
+        "user" => ("user", "user"),
+        "client" => ("address of the original client", "initial_address"),
+        "thr" => ("number of threads", "thread_ids"),
+        "mem" => ("peak memory usage", "peak_memory_usage"),
+        "disk" => (
+            "disk read+write per second",
+            "ReadBufferFromFileDescriptorReadBytes, WriteBufferFromFileDescriptorWriteBytes",
+        ),
+        "io" => (
+            "processed (uncompressed) bytes per second",
+            "SelectedBytes, InsertedBytes",
+        ),
+        "net" => (
+            "network send+receive per second (including S3)",
+            "NetworkSendBytes, NetworkReceiveBytes, ReadBufferFromS3Bytes, WriteBufferFromS3Bytes",
+        ),
+        "read_rows" => ("rows read", "read_rows"),
+        "read_bytes" => ("bytes read", "read_bytes"),
+        "progress" => (
+            "rows read of the estimated total rows to read",
+            "read_rows, total_rows_approx",
+        ),
+        "cache" => (
+            "filesystem cache hit ratio (by bytes)",
+            "CachedReadBufferReadFromCacheBytes, CachedReadBufferReadFromSourceBytes",
+        ),
+        "killed" => ("query was killed (KILL QUERY)", "exception_code = 394"),
+        "elapsed" => ("query duration", "elapsed, query_duration_ms"),
+        "query" => ("normalized query", "query"),
+        _ => ("", ""),
+    };
+}
+
+fn default_query_processes_columns(
+    options: &ViewOptions,
+    is_system_processes: bool,
+) -> Vec<&'static str> {
+    let mut columns = vec![
+        "query_id", "cpu", "io_wait", "cpu_wait", "user", "client", "thr", "mem", "disk", "io",
+        "net",
+    ];
+    if options.read_columns {
+        columns.push("read_rows");
+        columns.push("read_bytes");
+    }
+    // total_rows_approx is available only for system.processes
+    if is_system_processes {
+        columns.push("progress");
+    }
+    if options.cache_hit_ratio {
+        columns.push("cache");
+    }
+    // Available only for system.query_log
+    if !is_system_processes {
+        columns.push("killed");
+    }
+    columns.push("elapsed");
+    columns.push("query");
+    return columns;
+}
+
+fn add_query_processes_column(
+    table: &mut TableView<QueryProcess, QueryProcessesColumn>,
+    options: &ViewOptions,
+    name: &'static str,
+) {
+    if let Some((column, width)) = query_processes_column(options, name) {
+        table.add_column(column, name, |c| match width {
+            Some(width) => c.width(width),
+            None => c,
+        });
+    }
+}
+
+// Settings that affect how distributed queries are routed to shards/replicas
+const ROUTING_SETTINGS: &[(&str, &str)] = &[
+    (
+        "prefer_localhost_replica",
+        "local replica is used (if any), regardless of load_balancing",
+    ),
+    (
+        "load_balancing",
+        "algorithm to choose the replica of the shard",
+    ),
+    (
+        "load_balancing_first_offset",
+        "replica to use for load_balancing=in_order/first_or_random",
+    ),
+    (
+        "use_hedged_requests",
+        "query is sent to another replica if the first one is slow",
+    ),
+    (
+        "hedged_connection_timeout_ms",
+        "timeout to establish connection for hedged requests",
+    ),
+    (
+        "receive_data_timeout_ms",
+        "timeout for the first packet of data from the replica (hedged requests)",
+    ),
+    (
+        "connect_timeout_with_failover_ms",
+        "timeout to connect to the replica before trying the next one",
+    ),
+    (
+        "max_replica_delay_for_distributed_queries",
+        "replicas with bigger lag are not used",
+    ),
+    (
+        "fallback_to_stale_replicas_for_distributed_queries",
+        "lagging replicas are used if there are no up-to-date",
+    ),
+    (
+        "skip_unavailable_shards",
+        "unavailable shards are silently skipped",
+    ),
+    (
+        "max_parallel_replicas",
+        "number of replicas of the shard that are used to read in parallel",
+    ),
+    (
+        "allow_experimental_parallel_reading_from_replicas",
+        "parallel replicas (reading from multiple replicas)",
+    ),
+    (
+        "parallel_replicas_custom_key",
+        "parallel replicas split the data by custom key",
+    ),
+    (
+        "optimize_skip_unused_shards",
+        "shards are pruned by sharding key from WHERE",
+    ),
+    (
+        "distributed_product_mode",
+        "how subqueries over Distributed tables are rewritten",
+    ),
+    (
+        "prefer_global_in_and_join",
+        "IN/JOIN are replaced with GLOBAL IN/GLOBAL JOIN",
+    ),
+    (
+        "distributed_group_by_no_merge",
+        "results from shards are not merged on the initiator",
+    ),
+    (
+        "insert_distributed_sync",
+        "INSERT into Distributed waits for the shards",
+    ),
+    (
+        "distributed_foreground_insert",
+        "INSERT into Distributed waits for the shards",
+    ),
+];
+
+// Routing-relevant settings that had been changed for the query
+fn get_query_routing_settings(query: &QueryProcess) -> String {
+    let settings = ROUTING_SETTINGS
+        .iter()
+        .filter_map(|(name, description)| {
+            query
+                .settings
+                .get(*name)
+                .map(|value| (*name, value, *description))
+        })
+        .collect::<Vec<_>>();
+    if settings.is_empty() {
+        return "No routing settings had been changed for the query".to_string();
+    }
+
+    let width = settings
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap();
+    let mut lines = Vec::new();
+    for (name, value, description) in settings {
+        lines.push(format!(
+            "{:width$} = {} ({})",
+            name,
+            value,
+            description,
+            width = width
+        ));
+    }
+    return lines.join("\n");
+}
+
+// Summary that is shown on top of the ProfileEvents in "Query details"
+//
+// subqueries - all queries with the same initial_query_id (including the query itself)
+// subqueries_accumulated - ProfileEvents of the initial query includes subqueries (see
+// queries_sum_profile_events())
+fn get_query_details_summary(
+    query: &QueryProcess,
+    subqueries: &[QueryProcess],
+    subqueries_accumulated: bool,
+) -> String {
+    let fmt_bytes = SizeFormatter::new()
+        .with_base(Base::Base2)
+        .with_style(Style::Abbreviated);
+    let fmt_rows = SizeFormatter::new()
+        .with_base(Base::Base10)
+        .with_style(Style::Abbreviated);
+
+    let mut lines = Vec::<String>::new();
+    // query_id column may be truncated, so show it here as well
+    lines.push(format!("Query id: {}", query.query_id));
+    // Experimental features often explain instability, so they are shown on top
+    let experimental_settings = query.experimental_settings();
+    if !experimental_settings.is_empty() {
+        lines.push(format!(
+            "WARNING: experimental settings enabled: {}",
+            experimental_settings
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if !query.running {
+        lines.push(format!(
+            "Read: {} rows ({}), result: {} rows ({}), selectivity: {}",
+            fmt_rows.format(query.read_rows as i64),
+            fmt_bytes.format(query.read_bytes as i64),
+            fmt_rows.format(query.result_rows as i64),
+            fmt_bytes.format(query.result_bytes as i64),
+            query
+                .selectivity()
+                .map(|s| format!("{:.4} %", s))
+                .unwrap_or("n/a".to_string()),
+        ));
+    } else if let Some((name, value, limit)) = query.closest_limit() {
+        let format_value = |v: f64| match name {
+            "max_execution_time" => format!("{:.2} sec", v),
+            "max_memory_usage" => fmt_bytes.format(v as i64),
+            _ => fmt_rows.format(v as i64),
+        };
+        lines.push(format!(
+            "Closest limit: {} ({} of {}, {:.2} %)",
+            name,
+            format_value(value),
+            format_value(limit),
+            value / limit * 100.,
+        ));
+    }
+
+    if let Some((master, workers)) = query.thread_ids.split_first() {
+        // Keep it compact for queries with lots of threads
+        const MAX_WORKERS: usize = 10;
+        let mut workers_list = workers
+            .iter()
+            .take(MAX_WORKERS)
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        if workers.len() > MAX_WORKERS {
+            workers_list += ", ...";
+        }
+        lines.push(format!(
+            "Threads: {} (master: {}, workers: [{}])",
+            query.thread_ids.len(),
+            master,
+            workers_list
+        ));
+    }
+
+    let compile_functions = *query.profile_events.get("CompileFunction").unwrap_or(&0);
+    if compile_functions > 0 {
+        let compile_us = *query
+            .profile_events
+            .get("CompileExpressionsMicroseconds")
+            .unwrap_or(&0);
+        let compile_sec = compile_us as f64 / 1e6;
+        lines.push(format!(
+            "JIT: {} functions compiled in {:.3} sec{}",
+            compile_functions,
+            compile_sec,
+            // Compilation is done only once, so if it dominates the query, it is suspicious
+            if query.elapsed > 0. && compile_sec / query.elapsed > 0.5 {
+                " (dominates the query time!)"
+            } else {
+                ""
+            },
+        ));
+    }
+
+    if let Some(ratio) = query.cache_hit_ratio() {
+        let get_event = |name: &str| *query.profile_events.get(name).unwrap_or(&0) as i64;
+        lines.push(format!(
+            "Filesystem cache: hit ratio {:.2} % (from cache: {}, from source: {})",
+            ratio,
+            fmt_bytes.format(get_event("CachedReadBufferReadFromCacheBytes")),
+            fmt_bytes.format(get_event("CachedReadBufferReadFromSourceBytes")),
+        ));
+    }
+
+    if query.killed {
+        lines.push(format!("Killed: {}", query.kill_reason));
+    }
+
+    let overcommit_wait = query.memory_overcommit_wait_sec();
+    if overcommit_wait > 0. {
+        lines.push(format!(
+            "WARNING: throttled by memory overcommit, waited {:.3} sec ({:.2} % of elapsed)",
+            overcommit_wait,
+            if query.elapsed > 0. {
+                overcommit_wait / query.elapsed * 100.
+            } else {
+                0.
+            }
+        ));
+    }
+
+    let remote_endpoints = query.remote_endpoints();
+    if !remote_endpoints.is_empty() {
+        lines.push(format!("Remote endpoints: {}", remote_endpoints.join(", ")));
+    }
+
+    // Time that the initial query spent on top of the slowest shard (merging results, sending
+    // queries, waiting for connections and so on)
+    if query.is_initial_query {
+        let slowest = subqueries
+            .iter()
+            .filter(|q| !q.is_initial_query)
+            .max_by(|a, b| a.elapsed.total_cmp(&b.elapsed));
+        if let Some(slowest) = slowest {
+            let overhead = (query.elapsed - slowest.elapsed).max(0.);
+            lines.push(format!(
+                "Coordination overhead: {:.3} sec ({:.2} %), slowest shard: {:.3} sec on {}",
+                overhead,
+                if query.elapsed > 0. {
+                    overhead / query.elapsed * 100.
+                } else {
+                    0.
+                },
+                slowest.elapsed,
+                slowest.host_name,
+            ));
+        }
+    }
+
+    // Peak memory is per-host, so it cannot be summed like ProfileEvents
+    let mut hosts_memory = HashMap::<&str, i64>::new();
+    for q in subqueries {
+        let memory = hosts_memory.entry(q.host_name.as_str()).or_default();
+        *memory = (*memory).max(q.memory);
+    }
+    if hosts_memory.len() > 1 {
+        let mut hosts_memory = hosts_memory.into_iter().collect::<Vec<(&str, i64)>>();
+        hosts_memory.sort_by(|a, b| b.1.cmp(&a.1));
+        let width = hosts_memory.iter().map(|(h, _)| h.len()).max().unwrap();
+        lines.push(format!(
+            "Peak memory per host (max: {} on {}):",
+            fmt_bytes.format(hosts_memory[0].1),
+            hosts_memory[0].0,
+        ));
+        for (host, memory) in hosts_memory {
+            lines.push(format!(
+                "  {:width$}  {}",
+                host,
+                fmt_bytes.format(memory),
+                width = width
+            ));
+        }
+    }
+
+    // Network transfer per host (to find data skew/network hotspots)
+    let get_event = |q: &QueryProcess, name: &str| *q.profile_events.get(name).unwrap_or(&0);
+    let mut hosts_network = HashMap::<&str, (u64, u64)>::new();
+    for q in subqueries {
+        let mut send = get_event(q, "NetworkSendBytes");
+        let mut receive = get_event(q, "NetworkReceiveBytes");
+        if q.is_initial_query && subqueries_accumulated {
+            // Subtract subqueries to get the initial query own values
+            for sq in subqueries.iter().filter(|sq| !sq.is_initial_query) {
+                send = send.saturating_sub(get_event(sq, "NetworkSendBytes"));
+                receive = receive.saturating_sub(get_event(sq, "NetworkReceiveBytes"));
+            }
+        }
+        let network = hosts_network.entry(q.host_name.as_str()).or_default();
+        network.0 += send;
+        network.1 += receive;
+    }
+    if hosts_network.len() > 1 {
+        let mut hosts_network = hosts_network
+            .into_iter()
+            .collect::<Vec<(&str, (u64, u64))>>();
+        hosts_network.sort_by(|a, b| (b.1 .0 + b.1 .1).cmp(&(a.1 .0 + a.1 .1)));
+        let width = hosts_network.iter().map(|(h, _)| h.len()).max().unwrap();
+        lines.push(format!(
+            "Network per host (send/receive, max: {} on {}):",
+            fmt_bytes.format((hosts_network[0].1 .0 + hosts_network[0].1 .1) as i64),
+            hosts_network[0].0,
+        ));
+        for (host, (send, receive)) in hosts_network {
+            lines.push(format!(
+                "  {:width$}  {} / {}",
+                host,
+                fmt_bytes.format(send as i64),
+                fmt_bytes.format(receive as i64),
+                width = width
+            ));
+        }
+    }
+
+    return lines.join("\n");
+}
+
+// Memory of the query in relation to the server memory (from the summary)
+//
+// NOTE: in case of --cluster the summary is the sum over all hosts
+fn get_query_server_memory_summary(query: &QueryProcess, tracked: u64, os_total: u64) -> String {
+    let fmt_bytes = SizeFormatter::new()
+        .with_base(Base::Base2)
+        .with_style(Style::Abbreviated);
+    let percent = |total: u64| {
+        if total == 0 {
+            return "n/a".to_string();
+        }
+        return format!("{:.2} %", query.memory as f64 / total as f64 * 100.);
+    };
+    return format!(
+        "Memory: {} ({} of server tracked {}, {} of OS total {})",
+        fmt_bytes.format(query.memory),
+        percent(tracked),
+        fmt_bytes.format(tracked as i64),
+        percent(os_total),
+        fmt_bytes.format(os_total as i64),
+    );
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum QueryProcessesColumn {
     Selection,
@@ -92,11 +676,17 @@ pub enum QueryProcessesColumn {
     IOWait,
     CPUWait,
     User,
+    ClientAddress,
     Threads,
     Memory,
     DiskIO,
     IO,
     NetIO,
+    ReadRows,
+    ReadBytes,
+    Progress,
+    CacheHitRatio,
+    Killed,
     Elapsed,
     QueryId,
     Query,
@@ -133,12 +723,39 @@ impl TableViewItem<QueryProcessesColumn> for QueryProcess {
             QueryProcessesColumn::IOWait => format!("{:.1} %", self.io_wait()),
             QueryProcessesColumn::CPUWait => format!("{:.1} %", self.cpu_wait()),
             QueryProcessesColumn::User => self.user.clone(),
+            QueryProcessesColumn::ClientAddress => self.client_address.clone(),
             QueryProcessesColumn::Threads => self.threads.to_string(),
             QueryProcessesColumn::Memory => formatter.format(self.memory),
             QueryProcessesColumn::DiskIO => formatter.format(self.disk_io() as i64),
             QueryProcessesColumn::IO => formatter.format(self.io() as i64),
             QueryProcessesColumn::NetIO => formatter.format(self.net_io() as i64),
-            QueryProcessesColumn::Elapsed => format!("{:.2}", self.elapsed),
+            QueryProcessesColumn::ReadRows => self.read_rows.to_string(),
+            QueryProcessesColumn::ReadBytes => formatter.format(self.read_bytes as i64),
+            QueryProcessesColumn::Progress => self
+                .progress()
+                .map(|p| {
+                    const BAR_WIDTH: usize = 5;
+                    let filled = (p / 100. * BAR_WIDTH as f64).round() as usize;
+                    return format!(
+                        "{}{} {:.0}%",
+                        "█".repeat(filled),
+                        "░".repeat(BAR_WIDTH - filled),
+                        p
+                    );
+                })
+                .unwrap_or("-".to_string()),
+            QueryProcessesColumn::CacheHitRatio => self
+                .cache_hit_ratio()
+                .map(|r| format!("{:.1} %", r))
+                .unwrap_or("-".to_string()),
+            QueryProcessesColumn::Killed => {
+                if self.killed {
+                    "KILLED".to_string()
+                } else {
+                    "".to_string()
+                }
+            }
+            QueryProcessesColumn::Elapsed => self.duration_format.format(self.elapsed),
             QueryProcessesColumn::QueryId => {
                 if self.subqueries > 1 && self.is_initial_query {
                     return format!("-> {}", self.query_id);
@@ -162,11 +779,23 @@ impl TableViewItem<QueryProcessesColumn> for QueryProcess {
             QueryProcessesColumn::IOWait => self.io_wait().total_cmp(&other.io_wait()),
             QueryProcessesColumn::CPUWait => self.cpu_wait().total_cmp(&other.cpu_wait()),
             QueryProcessesColumn::User => self.user.cmp(&other.user),
+            QueryProcessesColumn::ClientAddress => self.client_address.cmp(&other.client_address),
             QueryProcessesColumn::Threads => self.threads.cmp(&other.threads),
             QueryProcessesColumn::Memory => self.memory.cmp(&other.memory),
             QueryProcessesColumn::DiskIO => self.disk_io().total_cmp(&other.disk_io()),
             QueryProcessesColumn::IO => self.io().total_cmp(&other.io()),
             QueryProcessesColumn::NetIO => self.net_io().total_cmp(&other.net_io()),
+            QueryProcessesColumn::ReadRows => self.read_rows.cmp(&other.read_rows),
+            QueryProcessesColumn::ReadBytes => self.read_bytes.cmp(&other.read_bytes),
+            QueryProcessesColumn::Progress => self
+                .progress()
+                .unwrap_or(-1.)
+                .total_cmp(&other.progress().unwrap_or(-1.)),
+            QueryProcessesColumn::CacheHitRatio => self
+                .cache_hit_ratio()
+                .unwrap_or(-1.)
+                .total_cmp(&other.cache_hit_ratio().unwrap_or(-1.)),
+            QueryProcessesColumn::Killed => self.killed.cmp(&other.killed),
             QueryProcessesColumn::Elapsed => self.elapsed.total_cmp(&other.elapsed),
             QueryProcessesColumn::QueryId => self.query_id.cmp(&other.query_id),
             QueryProcessesColumn::Query => self.normalized_query.cmp(&other.normalized_query),
@@ -183,13 +812,27 @@ pub struct ProcessesView {
     // For multi selection
     selected_query_ids: HashSet<String>,
     has_selection_column: bool,
+    // Shown by default in --cluster mode (can be toggled at runtime)
+    has_host_column: bool,
+    // Names of the columns (except for selection/host/Q#) in order (can be toggled at runtime)
+    columns: Vec<&'static str>,
     options: ViewOptions,
     // Is this running processes, or queries from system.query_log?
     is_system_processes: bool,
+    processes_type: Type,
+    view_name: &'static str,
+    // query_id to select once it will appear in the table (see select_query_id())
+    pending_selected_query_id: Option<String>,
     // Used to filter queries
     filter: Arc<Mutex<String>>,
+    // Used to filter already fetched queries (substring of query/user/host)
+    client_filter: String,
     // Number of queries to render
     limit: Arc<Mutex<u64>>,
+    // For "Show rows rate chart" (running queries only)
+    rows_rate_chart: Option<RowsRateChart>,
+    // Queries that had been killed by --auto-kill-over (to avoid sending KILL multiple times)
+    auto_killed_query_ids: HashSet<String>,
 
     #[allow(unused)]
     bg_runner: BackgroundRunner,
@@ -201,6 +844,20 @@ pub enum Type {
     SlowQueryLog,
     LastQueryLog,
 }
+impl Type {
+    /// Title of the view (reflects the subqueries mode)
+    pub fn title(&self, no_subqueries: bool) -> String {
+        let title = match self {
+            Type::ProcessList => "Queries",
+            Type::SlowQueryLog => "Slow queries",
+            Type::LastQueryLog => "Last queries",
+        };
+        if no_subqueries {
+            return format!("{} (raw subqueries)", title);
+        }
+        return title.to_string();
+    }
+}
 
 impl ProcessesView {
     inner_getters!(self.table: ExtTableView<QueryProcess, QueryProcessesColumn>);
@@ -214,19 +871,31 @@ impl ProcessesView {
 
         // TODO: write some closure to extract the field with type propagation.
         for i in 0..processes.row_count() {
+            let thread_ids = processes.get::<Vec<u64>, _>(i, "thread_ids")?;
             let mut query_process = QueryProcess {
                 selection: false,
                 host_name: processes.get::<_, _>(i, "host_name")?,
                 user: processes.get::<_, _>(i, "user")?,
-                threads: processes.get::<Vec<u64>, _>(i, "thread_ids")?.len(),
+                client_address: processes.get::<_, _>(i, "client_address")?,
+                threads: thread_ids.len(),
+                thread_ids,
                 memory: processes.get::<_, _>(i, "peak_memory_usage")?,
                 elapsed: processes.get::<_, _>(i, "elapsed")?,
+                read_rows: processes.get::<_, _>(i, "read_rows")?,
+                read_bytes: processes.get::<_, _>(i, "read_bytes")?,
+                total_rows_approx: processes.get::<_, _>(i, "total_rows_approx")?,
+                // Not available in system.processes (and may be missing in older versions)
+                result_rows: processes.get::<_, _>(i, "result_rows").unwrap_or_default(),
+                result_bytes: processes.get::<_, _>(i, "result_bytes").unwrap_or_default(),
                 query_start_time_microseconds: processes
                     .get::<DateTime<Tz>, _>(i, "query_start_time_microseconds")?
                     .with_timezone(&Local),
                 query_end_time_microseconds: processes
                     .get::<DateTime<Tz>, _>(i, "query_end_time_microseconds")?
                     .with_timezone(&Local),
+                // Not available in system.processes
+                killed: processes.get::<u8, _>(i, "is_killed").unwrap_or_default() == 1,
+                kill_reason: processes.get::<_, _>(i, "kill_reason").unwrap_or_default(),
                 subqueries: 1, // See queries_count_subqueries()
                 is_initial_query: processes.get::<u8, _>(i, "is_initial_query")? == 1,
                 initial_query_id: processes.get::<_, _>(i, "initial_query_id")?,
@@ -247,6 +916,7 @@ impl ProcessesView {
                 prev_profile_events: None,
 
                 running: self.is_system_processes,
+                duration_format: self.options.duration_format,
             };
 
             // FIXME: Shrinking is slow, but without it memory consumption is too high, 100-200x
@@ -274,11 +944,79 @@ impl ProcessesView {
         }
 
         self.selected_query_ids = new_selected_query_ids;
+        self.auto_kill();
+        self.update_rows_rate_chart();
         self.update_view();
+        self.context.lock().unwrap().set_last_update(self.view_name);
 
         return Ok(());
     }
 
+    fn update_rows_rate_chart(&mut self) {
+        let chart = match &mut self.rows_rate_chart {
+            Some(chart) => chart,
+            None => return,
+        };
+        // Keep the last values for finished queries
+        let query = match self.items.get(&chart.query_id) {
+            Some(query) => query,
+            None => return,
+        };
+        chart.update(query);
+
+        let text = chart.render();
+        self.context
+            .lock()
+            .unwrap()
+            .cb_sink
+            .send(Box::new(move |siv: &mut cursive::Cursive| {
+                siv.call_on_name("rows_rate_chart", |v: &mut views::TextView| {
+                    v.set_content(text);
+                });
+            }))
+            .unwrap();
+    }
+
+    fn auto_kill(&mut self) {
+        if !self.is_system_processes {
+            return;
+        }
+        let rule = match &self.options.auto_kill_over {
+            Some(rule) => rule.clone(),
+            None => return,
+        };
+
+        self.auto_killed_query_ids
+            .retain(|query_id| self.items.contains_key(query_id));
+
+        let mut context = self.context.lock().unwrap();
+        for q in self.items.values() {
+            // KILL of the initial query will kill all subqueries as well
+            if !q.is_initial_query || self.auto_killed_query_ids.contains(&q.query_id) {
+                continue;
+            }
+            let over_memory = rule.memory.is_some_and(|m| q.memory > m as i64);
+            let over_elapsed = rule.elapsed.is_some_and(|e| q.elapsed > e.as_secs_f64());
+            if !over_memory && !over_elapsed {
+                continue;
+            }
+
+            log::warn!(
+                "Auto-killing query {} (host: {}, user: {}, elapsed: {:.2} sec, memory: {}, query: {})",
+                q.query_id,
+                q.host_name,
+                q.user,
+                q.elapsed,
+                q.memory,
+                q.normalized_query,
+            );
+            context
+                .worker
+                .send(WorkerEvent::AutoKillQuery(q.query_id.clone()));
+            self.auto_killed_query_ids.insert(q.query_id.clone());
+        }
+    }
+
     fn update_view(&mut self) {
         let mut items = Vec::new();
         if let Some(query_id) = &self.query_id {
@@ -306,6 +1044,16 @@ impl ProcessesView {
             }
         }
 
+        // Client-side filter (does not require re-querying the server)
+        if !self.client_filter.is_empty() {
+            let filter = self.client_filter.to_lowercase();
+            items.retain(|q| {
+                return q.original_query.to_lowercase().contains(&filter)
+                    || q.user.to_lowercase().contains(&filter)
+                    || q.host_name.to_lowercase().contains(&filter);
+            });
+        }
+
         let inner_table = self.table.get_inner_mut().get_inner_mut();
 
         if !self.selected_query_ids.is_empty() {
@@ -322,6 +1070,18 @@ impl ProcessesView {
         }
 
         inner_table.set_items_stable(items);
+
+        if !self.items.is_empty() {
+            if let Some(query_id) = self.pending_selected_query_id.take() {
+                let index = inner_table
+                    .borrow_items()
+                    .iter()
+                    .position(|q| q.query_id == query_id);
+                if let Some(index) = index {
+                    inner_table.set_selected_item(index);
+                }
+            }
+        }
     }
 
     fn show_flamegraph(&mut self, tui: bool, trace_type: Option<TraceType>) -> Result<()> {
@@ -345,6 +1105,45 @@ impl ProcessesView {
         return Ok(());
     }
 
+    fn save_flamegraph(&mut self, trace_type: TraceType) -> Result<Option<EventResult>> {
+        let (query_ids, min_query_start_microseconds, max_query_end_microseconds) =
+            self.get_query_ids()?;
+        let default_path = format!(
+            "chdig-{}-{:?}.svg",
+            self.get_selected_query()?.query_id,
+            trace_type
+        )
+        .to_lowercase();
+        let context = self.context.clone();
+
+        return Ok(Some(EventResult::Consumed(Some(Callback::from_fn(
+            move |siv: &mut Cursive| {
+                let context = context.clone();
+                let trace_type = trace_type.clone();
+                let query_ids = query_ids.clone();
+                let save_cb = move |siv: &mut Cursive, path: &str| {
+                    context
+                        .lock()
+                        .unwrap()
+                        .worker
+                        .send(WorkerEvent::SaveQueryFlameGraph(
+                            trace_type.clone(),
+                            min_query_start_microseconds,
+                            max_query_end_microseconds,
+                            query_ids.clone(),
+                            path.to_string(),
+                        ));
+                    siv.pop_layer();
+                };
+                let view = EditView::new()
+                    .content(default_path.clone())
+                    .on_submit(save_cb)
+                    .min_width(40);
+                siv.add_layer(Dialog::around(view).title("Save flamegraph to (SVG)"));
+            },
+        )))));
+    }
+
     fn get_selected_query(&self) -> Result<QueryProcess> {
         let inner_table = self.table.get_inner().get_inner();
         let item_index = inner_table.item().ok_or(Error::msg("No query selected"))?;
@@ -354,6 +1153,21 @@ impl ProcessesView {
         return Ok(item.clone());
     }
 
+    /// Selected queries (multi selection), or the current query if nothing is selected.
+    fn get_selected_queries(&self) -> Result<Vec<QueryProcess>> {
+        if self.selected_query_ids.is_empty() {
+            return Ok(vec![self.get_selected_query()?]);
+        }
+        let mut queries = self
+            .items
+            .values()
+            .filter(|q| self.selected_query_ids.contains(&q.query_id))
+            .cloned()
+            .collect::<Vec<QueryProcess>>();
+        queries.sort_by_key(|q| q.query_start_time_microseconds);
+        return Ok(queries);
+    }
+
     fn get_query_ids(&self) -> Result<(Vec<String>, DateTime<Local>, Option<DateTime<Local>>)> {
         let selected_query = self.get_selected_query()?;
         let current_query_id = selected_query.query_id.clone();
@@ -425,6 +1239,177 @@ impl ProcessesView {
         ));
     }
 
+    /// Select the query by query_id (once it will be loaded)
+    pub fn select_query_id(&mut self, query_id: String) {
+        self.pending_selected_query_id = Some(query_id);
+        self.update_view();
+    }
+
+    pub fn set_filter(&mut self, filter: &str) {
+        log::info!("Set filter to '{}'", filter);
+        *self.filter.lock().unwrap() = filter.to_string();
+        // Trigger update
+        //
+        // NOTE: It will require first summary view and only after processes view, and this may
+        // be slow in case of cluster mode, and should be addressed.
+        self.bg_runner.schedule();
+    }
+
+    /// Show only queries of the initial_query_id (empty to show all queries)
+    pub fn set_initial_query_id(&mut self, query_id: &str) {
+        let query_id = query_id.trim();
+        log::info!("Set initial_query_id to '{}'", query_id);
+        if query_id.is_empty() {
+            self.query_id = None;
+        } else {
+            self.query_id = Some(query_id.to_string());
+        }
+        self.update_view();
+    }
+
+    pub fn set_client_filter(&mut self, filter: &str) {
+        log::info!("Set client filter to '{}'", filter);
+        self.client_filter = filter.to_string();
+        self.update_view();
+    }
+
+    fn toggle_duration_format(&mut self) {
+        let duration_format = match self.options.duration_format {
+            DurationFormat::Seconds => DurationFormat::Human,
+            DurationFormat::Human => DurationFormat::Seconds,
+        };
+        self.options.duration_format = duration_format;
+        // Remember for other views
+        self.context.lock().unwrap().options.view.duration_format = duration_format;
+        for item in self.items.values_mut() {
+            item.duration_format = duration_format;
+        }
+        self.update_view();
+    }
+
+    /// Tables referenced by the queries with the number of queries, sorted by number of queries
+    fn get_tables(&self) -> Vec<(String, usize)> {
+        let mut tables = HashMap::<String, usize>::new();
+        for q in self.items.values() {
+            // Subqueries had been counted in the initial query
+            if !q.is_initial_query {
+                continue;
+            }
+            for table in q.tables() {
+                *tables.entry(table).or_default() += 1;
+            }
+        }
+        let mut tables = tables.into_iter().collect::<Vec<(String, usize)>>();
+        tables.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        return tables;
+    }
+
+    /// Queries that had been waiting for locks grouped by the tables, as (table, [(lock wait ms,
+    /// query)]), sorted by the total lock wait
+    fn get_lock_contention(&self) -> Vec<(String, Vec<(u64, &QueryProcess)>)> {
+        let mut tables = HashMap::<String, Vec<(u64, &QueryProcess)>>::new();
+        for q in self.items.values() {
+            // Subqueries had been accounted in the initial query
+            if !q.is_initial_query {
+                continue;
+            }
+            let lock_wait_ms = q.lock_wait_ms();
+            if lock_wait_ms == 0 {
+                continue;
+            }
+            for table in q.tables() {
+                tables.entry(table).or_default().push((lock_wait_ms, q));
+            }
+        }
+        let total = |queries: &[(u64, &QueryProcess)]| queries.iter().map(|(w, _)| w).sum::<u64>();
+        let mut tables = tables.into_iter().collect::<Vec<_>>();
+        for (_, queries) in &mut tables {
+            queries.sort_by(|a, b| b.0.cmp(&a.0));
+        }
+        tables.sort_by(|a, b| total(&b.1).cmp(&total(&a.1)).then(a.0.cmp(&b.0)));
+        return tables;
+    }
+
+    /// Toggle host column (regardless of --cluster)
+    fn toggle_host_column(&mut self) {
+        let inner_table = self.table.get_inner_mut().get_inner_mut();
+        let column = if self.has_selection_column { 1 } else { 0 };
+        if self.has_host_column {
+            inner_table.remove_column(column);
+        } else {
+            inner_table.insert_column(column, QueryProcessesColumn::HostName, "host", |c| {
+                c.width(column_width(&self.options, "host", 8))
+            });
+        }
+        self.has_host_column = !self.has_host_column;
+    }
+
+    /// Show/hide the column (new columns are added before the query column)
+    fn toggle_column(&mut self, name: &'static str) {
+        let Some((column, width)) = query_processes_column(&self.options, name) else {
+            return;
+        };
+        let offset = self.has_selection_column as usize
+            + self.has_host_column as usize
+            + !self.options.no_subqueries as usize;
+
+        let inner_table = self.table.get_inner_mut().get_inner_mut();
+        if let Some(pos) = self.columns.iter().position(|c| *c == name) {
+            inner_table.remove_column(offset + pos);
+            self.columns.remove(pos);
+        } else {
+            let pos = self
+                .columns
+                .iter()
+                .position(|c| *c == "query")
+                .unwrap_or(self.columns.len());
+            inner_table.insert_column(offset + pos, column, name, |c| match width {
+                Some(width) => c.width(width),
+                None => c,
+            });
+            self.columns.insert(pos, name);
+        }
+    }
+
+    /// Toggle accumulating of subqueries metrics into the initial query (--no-subqueries)
+    fn toggle_subqueries(&mut self) {
+        let no_subqueries = !self.options.no_subqueries;
+        self.options.no_subqueries = no_subqueries;
+
+        let inner_table = self.table.get_inner_mut().get_inner_mut();
+        let column = if self.has_selection_column { 1 } else { 0 };
+        let column = if self.has_host_column {
+            column + 1
+        } else {
+            column
+        };
+        if no_subqueries {
+            inner_table.remove_column(column);
+        } else {
+            inner_table.insert_column(column, QueryProcessesColumn::SubQueries, "Q#", |c| {
+                c.width(column_width(&self.options, "Q#", 5))
+            });
+        }
+
+        // Metrics had been already accumulated, so reload the queries from scratch
+        self.items.clear();
+        self.bg_runner.schedule();
+
+        let title = self.processes_type.title(no_subqueries);
+        let dialog_name = format!("{}_dialog", self.view_name);
+        let mut context = self.context.lock().unwrap();
+        // Remember for other views (i.e. on switching with Tab)
+        context.options.view.no_subqueries = no_subqueries;
+        context
+            .cb_sink
+            .send(Box::new(move |siv: &mut cursive::Cursive| {
+                siv.call_on_name(&dialog_name, |dialog: &mut Dialog| {
+                    dialog.set_title(title);
+                });
+            }))
+            .unwrap();
+    }
+
     pub fn update_limit(&mut self, is_sub: bool) {
         let new_limit = if is_sub {
             self.limit.clone().lock().unwrap().saturating_sub(20)
@@ -453,6 +1438,7 @@ impl ProcessesView {
         let update_callback_context = context.clone();
         let update_callback_filter = filter.clone();
         let update_callback_limit = limit.clone();
+        let update_callback_processes_type = processes_type.clone();
         let update_callback = move || {
             let mut context = update_callback_context.lock().unwrap();
             let filter = update_callback_filter.lock().unwrap().clone();
@@ -461,7 +1447,7 @@ impl ProcessesView {
             let start_time = context.options.view.start;
             let end_time = context.options.view.end;
 
-            match processes_type {
+            match update_callback_processes_type {
                 Type::ProcessList => context
                     .worker
                     .send(WorkerEvent::UpdateProcessList(filter, limit)),
@@ -474,33 +1460,62 @@ impl ProcessesView {
             }
         };
 
+        let view_options = context.lock().unwrap().options.view.clone();
+
         let mut table = ExtTableView::<QueryProcess, QueryProcessesColumn>::default();
         let inner_table = table.get_inner_mut().get_inner_mut();
-        inner_table.add_column(QueryProcessesColumn::QueryId, "query_id", |c| c.width(12));
-        inner_table.add_column(QueryProcessesColumn::Cpu, "cpu", |c| c.width(8));
-        inner_table.add_column(QueryProcessesColumn::IOWait, "io_wait", |c| c.width(11));
-        inner_table.add_column(QueryProcessesColumn::CPUWait, "cpu_wait", |c| c.width(12));
-        inner_table.add_column(QueryProcessesColumn::User, "user", |c| c.width(8));
-        inner_table.add_column(QueryProcessesColumn::Threads, "thr", |c| c.width(6));
-        inner_table.add_column(QueryProcessesColumn::Memory, "mem", |c| c.width(6));
-        inner_table.add_column(QueryProcessesColumn::DiskIO, "disk", |c| c.width(7));
-        inner_table.add_column(QueryProcessesColumn::IO, "io", |c| c.width(7));
-        inner_table.add_column(QueryProcessesColumn::NetIO, "net", |c| c.width(6));
-        inner_table.add_column(QueryProcessesColumn::Elapsed, "elapsed", |c| c.width(11));
-        inner_table.add_column(QueryProcessesColumn::Query, "query", |c| c);
+        let columns = match &view_options.columns {
+            Some(columns) => columns
+                .iter()
+                .filter_map(|c| query_processes_column_name(c))
+                .collect(),
+            None => default_query_processes_columns(&view_options, is_system_processes),
+        };
+        for &name in &columns {
+            add_query_processes_column(inner_table, &view_options, name);
+        }
         inner_table.set_on_submit(|siv, _row, _index| {
             siv.on_event(Event::Char('l'));
         });
 
-        inner_table.sort_by(QueryProcessesColumn::Elapsed, Ordering::Greater);
-
-        let view_options = context.lock().unwrap().options.view.clone();
+        if columns.contains(&"elapsed") {
+            inner_table.sort_by(QueryProcessesColumn::Elapsed, Ordering::Greater);
+        }
 
         if !view_options.no_subqueries {
-            inner_table.insert_column(0, QueryProcessesColumn::SubQueries, "Q#", |c| c.width(5));
+            inner_table.insert_column(0, QueryProcessesColumn::SubQueries, "Q#", |c| {
+                c.width(column_width(&view_options, "Q#", 5))
+            });
         }
-        if context.lock().unwrap().options.clickhouse.cluster.is_some() {
-            inner_table.insert_column(0, QueryProcessesColumn::HostName, "host", |c| c.width(8));
+        let is_cluster = context.lock().unwrap().options.clickhouse.cluster.is_some();
+        if is_cluster {
+            inner_table.insert_column(0, QueryProcessesColumn::HostName, "host", |c| {
+                c.width(column_width(&view_options, "host", 8))
+            });
+        }
+
+        // --sort
+        if let Some(sort) = &view_options.sort {
+            let column = match sort.column.as_str() {
+                "host" if is_cluster => Some(QueryProcessesColumn::HostName),
+                "Q#" if !view_options.no_subqueries => Some(QueryProcessesColumn::SubQueries),
+                name if columns.iter().any(|c| *c == name) => {
+                    query_processes_column(&view_options, name).map(|(column, _)| column)
+                }
+                _ => None,
+            };
+            if let Some(column) = column {
+                inner_table.sort_by(
+                    column,
+                    if sort.ascending {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    },
+                );
+            } else {
+                log::warn!("No column {} for --sort in {}", sort.column, view_name);
+            }
         }
 
         let bg_runner_cv = context.lock().unwrap().background_runner_cv.clone();
@@ -514,10 +1529,18 @@ impl ProcessesView {
             query_id: None,
             selected_query_ids: HashSet::new(),
             has_selection_column: false,
+            has_host_column: is_cluster,
+            columns,
             options: view_options,
             is_system_processes,
+            processes_type,
+            view_name,
+            pending_selected_query_id: None,
             filter,
+            client_filter: String::new(),
             limit,
+            auto_killed_query_ids: HashSet::new(),
+            rows_rate_chart: None,
             bg_runner,
         };
 
@@ -534,7 +1557,7 @@ impl ProcessesView {
                 match result {
                     Err(err) => {
                         return Some(EventResult::with_cb_once(move |siv: &mut Cursive| {
-                            siv.add_layer(Dialog::info(err.to_string()));
+                            siv.show_action_error(err);
                         }));
                     }
                     Ok(event) => return event,
@@ -542,8 +1565,9 @@ impl ProcessesView {
             }
             return Some(EventResult::Ignored);
         });
-        log::debug!("Adding views actions");
         let mut context = context.lock().unwrap();
+        context.begin_view_actions(view_name);
+        let read_only = context.options.clickhouse.read_only;
         context.add_view_action(&mut event_view, "Select", ' ', |v| {
             let v = v.downcast_mut::<ProcessesView>().unwrap();
             let selected_query = v.get_selected_query()?;
@@ -574,20 +1598,40 @@ impl ProcessesView {
 
             return Ok(Some(EventResult::consumed()));
         });
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Show queries of initial_query_id",
+            move |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let query_id = v.query_id.clone().unwrap_or_default();
+                return Ok(Some(EventResult::Consumed(Some(Callback::from_fn(
+                    move |siv: &mut Cursive| {
+                        let on_submit = move |siv: &mut Cursive, text: &str| {
+                            siv.call_on_name(view_name, |v: &mut OnEventView<ProcessesView>| {
+                                v.get_inner_mut().set_initial_query_id(text);
+                            });
+                            siv.pop_layer();
+                        };
+                        siv.add_layer(
+                            Dialog::new()
+                                .title("initial_query_id (empty to show all queries)")
+                                .content(
+                                    EditView::new()
+                                        .content(query_id.clone())
+                                        .on_submit(on_submit)
+                                        .min_width(40),
+                                ),
+                        );
+                    },
+                )))));
+            },
+        );
         context.add_view_action(&mut event_view, "Filter", '/', move |_v| {
             return Ok(Some(EventResult::Consumed(Some(Callback::from_fn(
                 move |siv: &mut Cursive| {
                     let filter_cb = move |siv: &mut Cursive, text: &str| {
                         siv.call_on_name(view_name, |v: &mut OnEventView<ProcessesView>| {
-                            let v = v.get_inner_mut();
-                            log::info!("Set filter to '{}'", text);
-                            *v.filter.lock().unwrap() = text.to_string();
-                            // Trigger update
-                            //
-                            // NOTE: It will require first summary view and only after
-                            // processes view, and this may be slow in case of cluster mode, and
-                            // should be addressed.
-                            v.bg_runner.schedule();
+                            v.get_inner_mut().set_filter(text);
                         });
                         siv.pop_layer();
                     };
@@ -596,18 +1640,360 @@ impl ProcessesView {
                 },
             )))));
         });
+        context.add_view_action(&mut event_view, "Client-side filter", '\\', move |_v| {
+            return Ok(Some(EventResult::Consumed(Some(Callback::from_fn(
+                move |siv: &mut Cursive| {
+                    let filter_cb = move |siv: &mut Cursive, text: &str| {
+                        siv.call_on_name(view_name, |v: &mut OnEventView<ProcessesView>| {
+                            v.get_inner_mut().set_client_filter(text);
+                        });
+                        siv.pop_layer();
+                    };
+                    let view = OnEventView::new(EditView::new().on_submit(filter_cb).min_width(10));
+                    siv.add_layer(view);
+                },
+            )))));
+        });
+        context.add_view_action(
+            &mut event_view,
+            "Switch queries view (running/last/slow)",
+            Key::Tab,
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let filter = v.filter.lock().unwrap().clone();
+                let query_id = v.get_selected_query().ok().map(|q| q.query_id);
+                let processes_type = v.processes_type.clone();
+                let context = v.context.clone();
+                v.context
+                    .lock()
+                    .unwrap()
+                    .cb_sink
+                    .send(Box::new(move |siv: &mut cursive::Cursive| {
+                        let view_name = match processes_type {
+                            Type::ProcessList => {
+                                siv.show_clickhouse_last_query_log(context);
+                                "last_query_log"
+                            }
+                            Type::LastQueryLog => {
+                                siv.show_clickhouse_slow_query_log(context);
+                                "slow_query_log"
+                            }
+                            Type::SlowQueryLog => {
+                                siv.show_clickhouse_processes(context);
+                                "processes"
+                            }
+                        };
+                        siv.call_on_name(view_name, |v: &mut OnEventView<ProcessesView>| {
+                            let v = v.get_inner_mut();
+                            v.set_filter(&filter);
+                            if let Some(query_id) = query_id {
+                                v.select_query_id(query_id);
+                            }
+                        });
+                    }))
+                    .unwrap();
+
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action(
+            &mut event_view,
+            "Toggle subqueries accumulation",
+            'u',
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                v.toggle_subqueries();
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action(&mut event_view, "Toggle host column", 'H', |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            v.toggle_host_column();
+            return Ok(Some(EventResult::consumed()));
+        });
+        context.add_view_action(&mut event_view, "Toggle column", Event::AltChar('c'), |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let view_name = v.view_name;
+            let columns = v.columns.clone();
+            return Ok(Some(EventResult::Consumed(Some(Callback::from_fn(
+                move |siv: &mut Cursive| {
+                    let mut select = views::SelectView::new().autojump();
+                    for name in QUERIES_COLUMNS {
+                        let label = if columns.contains(&name) {
+                            format!("[x] {}", name)
+                        } else {
+                            format!("[ ] {}", name)
+                        };
+                        select.add_item(label, name);
+                    }
+                    select.set_on_submit(move |siv, name: &&'static str| {
+                        let name = *name;
+                        siv.pop_layer();
+                        siv.call_on_name(view_name, |v: &mut OnEventView<ProcessesView>| {
+                            v.get_inner_mut().toggle_column(name);
+                        });
+                    });
+                    siv.add_layer(Dialog::around(select.scrollable()).title("Toggle column"));
+                },
+            )))));
+        });
+        context.add_view_action(&mut event_view, "Show columns legend", '?', |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let width = v.columns.iter().map(|c| c.len()).max().unwrap_or_default();
+            let mut lines = Vec::new();
+            for &name in &v.columns {
+                let (description, source) = query_processes_column_description(name);
+                lines.push(format!(
+                    "{:width$}  {} ({})",
+                    name,
+                    description,
+                    source,
+                    width = width
+                ));
+            }
+            // cpu/io_wait/cpu_wait/disk/io/net are per second for running queries
+            lines.push(String::new());
+            lines.push(
+                "Rates are per second for running queries, and totals for finished queries"
+                    .to_string(),
+            );
+            let text = lines.join("\n");
+            return Ok(Some(EventResult::Consumed(Some(Callback::from_fn(
+                move |siv: &mut Cursive| {
+                    siv.add_layer(
+                        Dialog::around(views::TextView::new(text.clone()).scrollable())
+                            .title("Columns")
+                            .dismiss_button("Close"),
+                    );
+                },
+            )))));
+        });
+        context.add_view_action(&mut event_view, "Toggle durations format", 'h', |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            v.toggle_duration_format();
+            return Ok(Some(EventResult::consumed()));
+        });
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Show allocations by size",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let (query_ids, min_query_start_microseconds, max_query_end_microseconds) =
+                    v.get_query_ids()?;
+                v.context
+                    .lock()
+                    .unwrap()
+                    .worker
+                    .send(WorkerEvent::ShowQueryAllocations(
+                        min_query_start_microseconds,
+                        max_query_end_microseconds,
+                        query_ids,
+                    ));
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action_without_shortcut(&mut event_view, "Show exception", |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let selected_query = v.get_selected_query()?;
+            let end = if selected_query.running {
+                None
+            } else {
+                Some(selected_query.query_end_time_microseconds)
+            };
+            v.context
+                .lock()
+                .unwrap()
+                .worker
+                .send(WorkerEvent::ShowQueryException(
+                    selected_query.query_id,
+                    selected_query.query_start_time_microseconds,
+                    end,
+                ));
+            return Ok(Some(EventResult::consumed()));
+        });
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Show temporary/external tables",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let selected_query = v.get_selected_query()?;
+                let end = if selected_query.running {
+                    None
+                } else {
+                    Some(selected_query.query_end_time_microseconds)
+                };
+                v.context
+                    .lock()
+                    .unwrap()
+                    .worker
+                    .send(WorkerEvent::ShowQueryTemporaryTables(
+                        selected_query.query_id,
+                        selected_query.original_query,
+                        selected_query.query_start_time_microseconds,
+                        end,
+                    ));
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Export raw trace_log to TSV",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let (query_ids, min_query_start_microseconds, max_query_end_microseconds) =
+                    v.get_query_ids()?;
+                v.context
+                    .lock()
+                    .unwrap()
+                    .worker
+                    .send(WorkerEvent::ExportQueryTraceLog(
+                        min_query_start_microseconds,
+                        max_query_end_microseconds,
+                        query_ids,
+                    ));
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action_without_shortcut(&mut event_view, "Show threads timeline", |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let (query_ids, min_query_start_microseconds, max_query_end_microseconds) =
+                v.get_query_ids()?;
+            v.context
+                .lock()
+                .unwrap()
+                .worker
+                .send(WorkerEvent::ShowQueryThreadsTimeline(
+                    min_query_start_microseconds,
+                    max_query_end_microseconds,
+                    query_ids,
+                ));
+            return Ok(Some(EventResult::consumed()));
+        });
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Show tables of the queries",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let tables = v.get_tables();
+                if tables.is_empty() {
+                    return Err(Error::msg("No tables found in the queries"));
+                }
+                let view_name = v.view_name;
+
+                let width = tables
+                    .iter()
+                    .map(|(_, c)| c.to_string().len())
+                    .max()
+                    .unwrap();
+                let mut select = views::SelectView::<String>::new();
+                for (table, count) in tables {
+                    select.add_item(
+                        format!("{:>width$}  {}", count, table, width = width),
+                        table,
+                    );
+                }
+                select.set_on_submit(move |siv: &mut Cursive, table: &String| {
+                    let filter = format!("%{}%", table);
+                    siv.pop_layer();
+                    siv.call_on_name(view_name, |v: &mut OnEventView<ProcessesView>| {
+                        v.get_inner_mut().set_filter(&filter);
+                    });
+                });
+
+                v.context
+                    .lock()
+                    .unwrap()
+                    .cb_sink
+                    .send(Box::new(move |siv: &mut cursive::Cursive| {
+                        siv.add_layer(
+                            Dialog::around(select.scrollable()).title("Tables (number of queries)"),
+                        );
+                    }))
+                    .unwrap();
+
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action_without_shortcut(&mut event_view, "Show lock contention", |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let tables = v.get_lock_contention();
+            if tables.is_empty() {
+                return Err(Error::msg("No queries waiting for locks"));
+            }
+
+            let mut lines = Vec::<String>::new();
+            for (table, queries) in tables {
+                lines.push(format!(
+                    "{}: {} queries waiting ({} ms total)",
+                    table,
+                    queries.len(),
+                    queries.iter().map(|(w, _)| w).sum::<u64>(),
+                ));
+                for (lock_wait_ms, q) in queries {
+                    lines.push(format!(
+                        "  {:>8} ms  {}  {}",
+                        lock_wait_ms,
+                        q.query_id,
+                        q.normalized_query.chars().take(80).collect::<String>(),
+                    ));
+                }
+                lines.push(String::new());
+            }
+            let text = lines.join("\n");
+
+            v.context
+                .lock()
+                .unwrap()
+                .cb_sink
+                .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    siv.add_layer(
+                        Dialog::around(views::TextView::new(text).scrollable())
+                            .title("Lock contention (by table)"),
+                    );
+                }))
+                .unwrap();
+
+            return Ok(Some(EventResult::consumed()));
+        });
         context.add_view_action(&mut event_view, "Query details", 'D', |v| {
             let v = v.downcast_mut::<ProcessesView>().unwrap();
             let selected_query = v.get_selected_query()?;
+            let subqueries = v
+                .items
+                .values()
+                .filter(|q| q.initial_query_id == selected_query.initial_query_id)
+                .cloned()
+                .collect::<Vec<QueryProcess>>();
+            let summary =
+                get_query_details_summary(&selected_query, &subqueries, !v.options.no_subqueries);
+            let context = v.context.clone();
             v.context
                 .lock()
                 .unwrap()
                 .cb_sink
                 .send(Box::new(move |siv: &mut cursive::Cursive| {
+                    let mut summary = summary;
+                    if selected_query.running {
+                        let memory = siv
+                            .call_on_name("summary", |v: &mut SummaryView| v.get_memory())
+                            .flatten();
+                        if let Some((tracked, os_total)) = memory {
+                            summary.push('\n');
+                            summary.push_str(&get_query_server_memory_summary(
+                                &selected_query,
+                                tracked,
+                                os_total,
+                            ));
+                        }
+                    }
                     siv.add_layer(views::Dialog::around(
-                        ProcessView::new(selected_query)
-                            .with_name("process")
-                            .min_size((70, 35)),
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new(summary))
+                            .child(
+                                ProcessView::new(context, selected_query)
+                                    .with_name("process")
+                                    .min_size((70, 35)),
+                            ),
                     ));
                 }))
                 .unwrap();
@@ -690,6 +2076,37 @@ impl ProcessesView {
 
             return Ok(Some(EventResult::consumed()));
         });
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Show durations of similar queries",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let selected_query = v.get_selected_query()?;
+                let mut context = v.context.lock().unwrap();
+                let (start, end) = (context.options.view.start, context.options.view.end);
+                context.worker.send(WorkerEvent::ShowQueryDurations(
+                    selected_query.original_query,
+                    start,
+                    end,
+                ));
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action_without_shortcut(&mut event_view, "Show slowest processors", |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let (query_ids, min_query_start_microseconds, max_query_end_microseconds) =
+                v.get_query_ids()?;
+            v.context
+                .lock()
+                .unwrap()
+                .worker
+                .send(WorkerEvent::ShowQuerySlowestProcessors(
+                    min_query_start_microseconds,
+                    max_query_end_microseconds,
+                    query_ids,
+                ));
+            return Ok(Some(EventResult::consumed()));
+        });
         context.add_view_action(&mut event_view, "Query views", 'v', |v| {
             let v = v.downcast_mut::<ProcessesView>().unwrap();
             let (query_ids, min_query_start_microseconds, max_query_end_microseconds) = v.get_query_ids()?;
@@ -808,31 +2225,58 @@ impl ProcessesView {
                 return Ok(Some(EventResult::consumed()));
             },
         );
-        context.add_view_action(
+        context.add_view_action_without_shortcut(
             &mut event_view,
-            "Edit query and execute",
-            Event::AltChar('E'),
+            "Save CPU flamegraph to file",
             |v| {
                 let v = v.downcast_mut::<ProcessesView>().unwrap();
-                let selected_query = v.get_selected_query()?;
-                let query = selected_query.original_query.clone();
-                let database = selected_query.current_database.clone();
-                let settings = selected_query.settings.clone();
-                let mut context_locked = v.context.lock().unwrap();
-
-                // TODO: prepend database
-                let query = edit_query(&query, &settings)?;
-
-                // TODO: add support for Log packets into clickhouse-rs and execute query with logging in place
-                context_locked
-                    .worker
-                    .send(WorkerEvent::ExecuteQuery(database, query));
-
-                return Ok(Some(EventResult::Consumed(Some(Callback::from_fn_once(
-                    |siv| siv.clear(),
-                )))));
+                return v.save_flamegraph(TraceType::CPU);
+            },
+        );
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Save Real flamegraph to file",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                return v.save_flamegraph(TraceType::Real);
             },
         );
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Save memory flamegraph to file",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                return v.save_flamegraph(TraceType::Memory);
+            },
+        );
+        // Mutating actions are not available in --read-only mode
+        if !read_only {
+            context.add_view_action(
+                &mut event_view,
+                "Edit query and execute",
+                Event::AltChar('E'),
+                |v| {
+                    let v = v.downcast_mut::<ProcessesView>().unwrap();
+                    let selected_query = v.get_selected_query()?;
+                    let query = selected_query.original_query.clone();
+                    let database = selected_query.current_database.clone();
+                    let settings = selected_query.settings.clone();
+                    let mut context_locked = v.context.lock().unwrap();
+
+                    // TODO: prepend database
+                    let query = edit_query(&query, &settings)?;
+
+                    // TODO: add support for Log packets into clickhouse-rs and execute query with logging in place
+                    context_locked
+                        .worker
+                        .send(WorkerEvent::ExecuteQuery(database, query));
+
+                    return Ok(Some(EventResult::Consumed(Some(Callback::from_fn_once(
+                        |siv| siv.clear(),
+                    )))));
+                },
+            );
+        }
         context.add_view_action(&mut event_view, "Show query", 'S', |v| {
             let v = v.downcast_mut::<ProcessesView>().unwrap();
             let selected_query = v.get_selected_query()?;
@@ -841,7 +2285,8 @@ impl ProcessesView {
             let settings = selected_query.settings.clone();
 
             let query = get_query(&query, &settings);
-            let query = format!("USE {};\n{}", database, query);
+            let query = highlight_sql(&format!("USE {};\n{}", database, query))?;
+            let wrap = v.context.lock().unwrap().options.view.wrap;
 
             v.context
                 .lock()
@@ -852,13 +2297,46 @@ impl ProcessesView {
                         views::LinearLayout::vertical()
                             .child(views::TextView::new("Query:").center())
                             .child(views::DummyView.fixed_height(1))
-                            .child(views::TextView::new(query)),
+                            .child(LogView::pager_with_wrap(query, wrap)),
                     ));
                 }))
                 .unwrap();
 
             return Ok(Some(EventResult::consumed()));
         });
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Show query with SET statements",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let selected_query = v.get_selected_query()?;
+                let query = get_query_with_set_statements(
+                    &format!("{};", selected_query.original_query.trim_end_matches(';')),
+                    &selected_query.settings,
+                );
+                let query = highlight_sql(&format!(
+                    "USE {};\n{}",
+                    selected_query.current_database, query
+                ))?;
+                let wrap = v.context.lock().unwrap().options.view.wrap;
+
+                v.context
+                    .lock()
+                    .unwrap()
+                    .cb_sink
+                    .send(Box::new(move |siv: &mut cursive::Cursive| {
+                        siv.add_layer(views::Dialog::around(
+                            views::LinearLayout::vertical()
+                                .child(views::TextView::new("Query:").center())
+                                .child(views::DummyView.fixed_height(1))
+                                .child(LogView::pager_with_wrap(query, wrap)),
+                        ));
+                    }))
+                    .unwrap();
+
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
         context.add_view_action(&mut event_view, "EXPLAIN SYNTAX", 's', |v| {
             let v = v.downcast_mut::<ProcessesView>().unwrap();
             let selected_query = v.get_selected_query()?;
@@ -915,7 +2393,7 @@ impl ProcessesView {
                 return Ok(Some(EventResult::consumed()));
             },
         );
-        context.add_view_action(&mut event_view, "EXPLAIN INDEXES", 'I', |v| {
+        context.add_view_action_without_shortcut(&mut event_view, "EXPLAIN PLAN as Mermaid", |v| {
             let v = v.downcast_mut::<ProcessesView>().unwrap();
             let selected_query = v.get_selected_query()?;
             let query = selected_query.original_query.clone();
@@ -923,44 +2401,179 @@ impl ProcessesView {
             let mut context_locked = v.context.lock().unwrap();
             context_locked
                 .worker
-                .send(WorkerEvent::ExplainPlanIndexes(database, query));
+                .send(WorkerEvent::ExplainMermaid(database, query, false));
 
             return Ok(Some(EventResult::consumed()));
         });
-        context.add_view_action(&mut event_view, "KILL query", 'K', |v| {
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "EXPLAIN PIPELINE as Mermaid",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let selected_query = v.get_selected_query()?;
+                let query = selected_query.original_query.clone();
+                let database = selected_query.current_database.clone();
+                let mut context_locked = v.context.lock().unwrap();
+                context_locked
+                    .worker
+                    .send(WorkerEvent::ExplainMermaid(database, query, true));
+
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Show settings profile/constraints",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let selected_query = v.get_selected_query()?;
+                v.context
+                    .lock()
+                    .unwrap()
+                    .worker
+                    .send(WorkerEvent::ShowSettingsProfile(
+                        selected_query.user.clone(),
+                        selected_query.settings.clone(),
+                    ));
+
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action_without_shortcut(&mut event_view, "Show routing settings", |v| {
             let v = v.downcast_mut::<ProcessesView>().unwrap();
             let selected_query = v.get_selected_query()?;
-            let query_id = selected_query.query_id.clone();
-            let context_copy = v.context.clone();
+            let text = get_query_routing_settings(&selected_query);
             v.context
                 .lock()
                 .unwrap()
                 .cb_sink
                 .send(Box::new(move |siv: &mut cursive::Cursive| {
-                    siv.add_layer(
-                        views::Dialog::new()
-                            .title(format!(
-                                "Are you sure you want to KILL QUERY with query_id = {}",
-                                query_id
-                            ))
-                            .button("Yes, I'm sure", move |s| {
-                                context_copy
-                                    .lock()
-                                    .unwrap()
-                                    .worker
-                                    .send(WorkerEvent::KillQuery(query_id.clone()));
-                                // TODO: wait for the KILL
-                                s.pop_layer();
-                            })
-                            .button("Cancel", |s| {
-                                s.pop_layer();
-                            }),
-                    );
+                    siv.add_layer(views::Dialog::around(
+                        views::LinearLayout::vertical()
+                            .child(views::TextView::new("Routing settings").center())
+                            .child(views::DummyView.fixed_height(1))
+                            .child(views::TextView::new(text)),
+                    ));
                 }))
                 .unwrap();
 
             return Ok(Some(EventResult::consumed()));
         });
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Estimated vs actual parts",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let selected_query = v.get_selected_query()?;
+                // Actual values are known only once the query is finished
+                if selected_query.running {
+                    return Err(Error::msg(
+                        "Available only for finished queries (slow/last queries)",
+                    ));
+                }
+                let get_event = |name: &str| *selected_query.profile_events.get(name).unwrap_or(&0);
+                v.context
+                    .lock()
+                    .unwrap()
+                    .worker
+                    .send(WorkerEvent::IndexesEstimation(
+                        selected_query.current_database.clone(),
+                        selected_query.original_query.clone(),
+                        get_event("SelectedParts"),
+                        get_event("SelectedMarks"),
+                    ));
+
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action(&mut event_view, "EXPLAIN INDEXES", 'I', |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let selected_query = v.get_selected_query()?;
+            let query = selected_query.original_query.clone();
+            let database = selected_query.current_database.clone();
+            let mut context_locked = v.context.lock().unwrap();
+            context_locked
+                .worker
+                .send(WorkerEvent::ExplainPlanIndexes(database, query));
+
+            return Ok(Some(EventResult::consumed()));
+        });
+        context.add_view_action_without_shortcut(
+            &mut event_view,
+            "Export selected queries to JSON",
+            |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let queries = v.get_selected_queries()?;
+                let path = save_json("chdig-queries-", &queries)?;
+                let message = format!("{} queries had been exported to {}", queries.len(), path);
+                log::info!("{}", message);
+                v.context
+                    .lock()
+                    .unwrap()
+                    .cb_sink
+                    .send(Box::new(move |siv: &mut cursive::Cursive| {
+                        siv.add_layer(views::Dialog::info(message));
+                    }))
+                    .unwrap();
+
+                return Ok(Some(EventResult::consumed()));
+            },
+        );
+        context.add_view_action_without_shortcut(&mut event_view, "Copy query_id", |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let selected_query = v.get_selected_query()?;
+            copy_to_clipboard(selected_query.query_id)?;
+            return Ok(Some(EventResult::consumed()));
+        });
+        context.add_view_action_without_shortcut(&mut event_view, "Copy KILL command", |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let selected_query = v.get_selected_query()?;
+            let query = v
+                .context
+                .lock()
+                .unwrap()
+                .clickhouse
+                .get_kill_query(&selected_query.query_id);
+            copy_to_clipboard(query)?;
+            return Ok(Some(EventResult::consumed()));
+        });
+        // Mutating actions are not available in --read-only mode
+        if !read_only {
+            context.add_view_action(&mut event_view, "KILL query", 'K', |v| {
+                let v = v.downcast_mut::<ProcessesView>().unwrap();
+                let selected_query = v.get_selected_query()?;
+                let query_id = selected_query.query_id.clone();
+                let context_copy = v.context.clone();
+                v.context
+                    .lock()
+                    .unwrap()
+                    .cb_sink
+                    .send(Box::new(move |siv: &mut cursive::Cursive| {
+                        siv.add_layer(
+                            views::Dialog::new()
+                                .title(format!(
+                                    "Are you sure you want to KILL QUERY with query_id = {}",
+                                    query_id
+                                ))
+                                .button("Yes, I'm sure", move |s| {
+                                    context_copy
+                                        .lock()
+                                        .unwrap()
+                                        .worker
+                                        .send(WorkerEvent::KillQuery(query_id.clone()));
+                                    // TODO: wait for the KILL
+                                    s.pop_layer();
+                                })
+                                .button("Cancel", |s| {
+                                    s.pop_layer();
+                                }),
+                        );
+                    }))
+                    .unwrap();
+
+                return Ok(Some(EventResult::consumed()));
+            });
+        }
         context.add_view_action(&mut event_view, "Show query logs", 'l', |v| {
             let v = v.downcast_mut::<ProcessesView>().unwrap();
             let (query_ids, min_query_start_microseconds, max_query_end_microseconds) =
@@ -1016,14 +2629,35 @@ impl ProcessesView {
                 return Ok(Some(EventResult::consumed()));
             },
         );
+        context.add_view_action(&mut event_view, "Set time frame to the query", 'w', |v| {
+            let v = v.downcast_mut::<ProcessesView>().unwrap();
+            let selected_query = v.get_selected_query()?;
+            if selected_query.running {
+                return Err(Error::msg("Query is still running"));
+            }
+            let buffer = chrono::Duration::try_seconds(QUERY_TIME_DRIFT_BUFFER_SECONDS).unwrap();
+            let start = selected_query.query_start_time_microseconds - buffer;
+            let end = selected_query.query_end_time_microseconds + buffer;
+
+            let mut context = v.context.lock().unwrap();
+            log::debug!("Set time frame to ({}, {})", start, end);
+            context.options.view.start = start;
+            context.options.view.end = end;
+            context.options.view.end_is_now = false;
+            save_time_frame(&context.options.view);
+            context.trigger_view_refresh();
+            return Ok(Some(EventResult::consumed()));
+        });
         return event_view;
     }
 }
 
 impl Drop for ProcessesView {
     fn drop(&mut self) {
-        log::debug!("Removing views actions");
-        self.context.lock().unwrap().view_actions.clear();
+        self.context
+            .lock()
+            .unwrap()
+            .remove_view_actions(self.view_name);
     }
 }
 
@@ -1,7 +1,7 @@
 use std::cmp::Ordering;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Error, Result};
 use size::{Base, SizeFormatter, Style};
 
 use crate::interpreter::{clickhouse::Columns, BackgroundRunner, ContextArc, WorkerEvent};
@@ -25,6 +25,8 @@ pub enum Field {
     Int32(i32),
     Int8(i8),
     DateTime(DateTime<Local>),
+    Null,
+    Array(Vec<Field>),
     // TODO: support more types
 }
 impl std::fmt::Display for Field {
@@ -58,6 +60,39 @@ impl std::fmt::Display for Field {
             Self::Int32(ref value) => write!(f, "{}", value),
             Self::Int8(ref value) => write!(f, "{}", value),
             Self::DateTime(ref value) => write!(f, "{}", value),
+            Self::Null => write!(f, "∅"),
+            Self::Array(ref values) => write!(
+                f,
+                "[{}] ({})",
+                values
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                values.len()
+            ),
+        }
+    }
+}
+
+impl Field {
+    /// Raw value (without human formatting) for the JSON
+    pub fn to_json(&self) -> serde_json::Value {
+        match *self {
+            Self::String(ref value) => serde_json::Value::from(value.clone()),
+            Self::Float64(ref value) => serde_json::Value::from(*value),
+            Self::Float32(ref value) => serde_json::Value::from(*value),
+            Self::UInt64(ref value) => serde_json::Value::from(*value),
+            Self::UInt32(ref value) => serde_json::Value::from(*value),
+            Self::UInt8(ref value) => serde_json::Value::from(*value),
+            Self::Int64(ref value) => serde_json::Value::from(*value),
+            Self::Int32(ref value) => serde_json::Value::from(*value),
+            Self::Int8(ref value) => serde_json::Value::from(*value),
+            Self::DateTime(ref value) => serde_json::Value::from(value.to_rfc3339()),
+            Self::Null => serde_json::Value::Null,
+            Self::Array(ref values) => {
+                serde_json::Value::Array(values.iter().map(|v| v.to_json()).collect())
+            }
         }
     }
 }
@@ -99,14 +134,19 @@ impl TableViewItem<u8> for Row {
 type RowCallback = Arc<dyn Fn(&mut Cursive, Row) + Send + Sync>;
 
 pub struct QueryResultView {
+    context: ContextArc,
+    view_name: &'static str,
     table: ExtTableView<Row, u8>,
+    items: Vec<Row>,
+    filter: String,
+    // Server side filter (WHERE on top of the query)
+    query_filter: Arc<Mutex<String>>,
 
     // Number of first columns to compare for PartialEq
     columns_to_compare: usize,
-    columns: Vec<&'static str>,
+    columns: Vec<String>,
     on_submit: Option<RowCallback>,
 
-    #[allow(unused)]
     bg_runner: BackgroundRunner,
 }
 
@@ -116,38 +156,134 @@ impl QueryResultView {
 
         for i in 0..block.row_count() {
             let mut row = Row::default();
-            for &column in &self.columns {
+            for column in self.columns.iter().map(String::as_str) {
                 let sql_column = block
                     .columns()
                     .iter()
                     .find(|c| c.name() == column)
                     .ok_or(anyhow!("Cannot get {} column", column))?;
-                let field = match sql_column.sql_type() {
-                    SqlType::String => Field::String(block.get::<_, _>(i, column)?),
-                    SqlType::Float64 => Field::Float64(block.get::<_, _>(i, column)?),
-                    SqlType::Float32 => Field::Float32(block.get::<_, _>(i, column)?),
-                    SqlType::UInt64 => Field::UInt64(block.get::<_, _>(i, column)?),
-                    SqlType::UInt32 => Field::UInt32(block.get::<_, _>(i, column)?),
-                    SqlType::UInt8 => Field::UInt8(block.get::<_, _>(i, column)?),
-                    SqlType::Int64 => Field::Int64(block.get::<_, _>(i, column)?),
-                    SqlType::Int32 => Field::Int32(block.get::<_, _>(i, column)?),
-                    SqlType::Int8 => Field::Int8(block.get::<_, _>(i, column)?),
-                    SqlType::DateTime(_) => Field::DateTime(
-                        block
-                            .get::<DateTime<Tz>, _>(i, column)?
-                            .with_timezone(&Local),
-                    ),
-                    _ => unreachable!("Type for column {} not implemented", column),
-                };
-                row.0.push(field);
+                row.0
+                    .push(get_field(&block, i, column, sql_column.sql_type())?);
             }
             row.1 = self.columns_to_compare;
             items.push(row);
         }
 
+        self.items = items;
+        self.update_view();
+        self.context.lock().unwrap().set_last_update(self.view_name);
+
+        return Ok(());
+    }
+
+    fn update_view(&mut self) {
+        let items = if self.filter.is_empty() {
+            self.items.clone()
+        } else {
+            self.items
+                .iter()
+                .filter(|row| row.0.iter().any(|f| f.to_string().contains(&self.filter)))
+                .cloned()
+                .collect()
+        };
+
         let inner_table = self.table.get_inner_mut().get_inner_mut();
         inner_table.set_items_stable(items);
+    }
+
+    /// Client side filter (substring match in any column)
+    pub fn set_filter(&mut self, filter: &str) {
+        log::info!("Set filter to '{}'", filter);
+        self.filter = filter.to_string();
+        self.update_view();
+    }
+
+    /// Get column names of the block, and check that all of them are supported.
+    pub fn get_columns(block: &Columns) -> Result<Vec<String>> {
+        let mut columns = Vec::new();
+        for column in block.columns() {
+            if block.row_count() > 0 {
+                get_field(block, 0, column.name(), column.sql_type())?;
+            }
+            columns.push(column.name().to_string());
+        }
+        return Ok(columns);
+    }
+
+    /// Server side filter (WHERE expression), empty to reset
+    pub fn set_query_filter(&mut self, filter: String) {
+        log::info!("Set query filter to '{}'", filter);
+        *self.query_filter.lock().unwrap() = filter;
+        self.bg_runner.schedule();
+    }
+
+    pub fn get_context(&self) -> ContextArc {
+        return self.context.clone();
+    }
+
+    pub fn get_selected_row(&self) -> Result<Row> {
+        let inner_table = self.table.get_inner().get_inner();
+        let item_index = inner_table.item().ok_or(Error::msg("No row selected"))?;
+        let item = inner_table
+            .borrow_item(item_index)
+            .ok_or(Error::msg("No such row anymore"))?;
+        return Ok(item.clone());
+    }
+
+    /// Selected row as JSON object (column name -> value)
+    pub fn get_selected_row_json(&self) -> Result<String> {
+        let row = self.get_selected_row()?;
+        let mut object = serde_json::Map::new();
+        for (column, field) in self.columns.iter().zip(row.0.iter()) {
+            object.insert(column.to_string(), field.to_json());
+        }
+        return Ok(serde_json::to_string_pretty(&object)?);
+    }
+
+    /// Column names and raw values of the rows that are shown (i.e. with filter applied)
+    pub fn get_rows_json(&self) -> (Vec<String>, Vec<Vec<serde_json::Value>>) {
+        let header = self.columns.iter().map(|c| c.to_string()).collect();
+        let rows = self
+            .table
+            .get_inner()
+            .get_inner()
+            .borrow_items()
+            .iter()
+            .map(|row| row.0.iter().map(|f| f.to_json()).collect())
+            .collect();
+        return (header, rows);
+    }
+
+    /// Get the value of the column (by name) for the selected row.
+    pub fn get_selected_column(&self, column: &str) -> Result<Field> {
+        let index = self
+            .columns
+            .iter()
+            .position(|c| *c == column)
+            .ok_or(anyhow!("No such column {}", column))?;
+        let row = self.get_selected_row()?;
+        return Ok(row.0[index].clone());
+    }
 
+    /// Change the default sort order (--sort takes precedence)
+    pub fn set_default_sort(&mut self, column: &str, ascending: bool) -> Result<()> {
+        if self.context.lock().unwrap().options.view.sort.is_some() {
+            return Ok(());
+        }
+        let index = self
+            .columns
+            .iter()
+            .position(|c| *c == column)
+            .ok_or(anyhow!("No such column {}", column))?;
+        let order = if ascending {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        };
+        self.table
+            .get_inner_mut()
+            .get_inner_mut()
+            .sort_by(index as u8, order);
         return Ok(());
     }
 
@@ -161,39 +297,111 @@ impl QueryResultView {
     pub fn new(
         context: ContextArc,
         view_name: &'static str,
-        sort_by: &'static str,
-        columns: Vec<&'static str>,
+        sort_by: &str,
+        columns: Vec<&str>,
+        columns_to_compare: usize,
+        query: String,
+    ) -> Result<Self> {
+        let columns = parse_columns(&columns);
+        return Self::create(
+            context,
+            view_name,
+            sort_by,
+            columns,
+            columns_to_compare,
+            query,
+            true,
+        );
+    }
+
+    /// Like new(), but the column names are used as is (i.e. names from the block of an arbitrary
+    /// query), and there are no private columns
+    pub fn new_with_column_names(
+        context: ContextArc,
+        view_name: &'static str,
+        sort_by: &str,
+        columns: Vec<String>,
+        columns_to_compare: usize,
+        query: String,
+    ) -> Result<Self> {
+        return Self::create(
+            context,
+            view_name,
+            sort_by,
+            columns,
+            columns_to_compare,
+            query,
+            false,
+        );
+    }
+
+    fn create(
+        context: ContextArc,
+        view_name: &'static str,
+        sort_by: &str,
+        columns: Vec<String>,
         columns_to_compare: usize,
         query: String,
+        private_columns: bool,
     ) -> Result<Self> {
         let delay = context.lock().unwrap().options.view.delay_interval;
 
+        let query_filter = Arc::new(Mutex::new(String::new()));
+
         let update_callback_context = context.clone();
+        let update_callback_query_filter = query_filter.clone();
         let update_callback = move || {
+            let filter = update_callback_query_filter.lock().unwrap().clone();
+            let query = if filter.is_empty() {
+                query.clone()
+            } else {
+                format!("SELECT * FROM ({}) WHERE {}", query, filter)
+            };
             update_callback_context
                 .lock()
                 .unwrap()
                 .worker
-                .send(WorkerEvent::ViewQuery(view_name, query.clone()));
+                .send(WorkerEvent::ViewQuery(view_name, query));
         };
 
-        let columns = parse_columns(&columns);
+        let is_private = |column: &str| private_columns && column.starts_with('_');
 
         let mut table = ExtTableView::<Row, u8>::default();
         let inner_table = table.get_inner_mut().get_inner_mut();
         for (i, column) in columns.iter().enumerate() {
-            // Private column
-            if column.starts_with('_') {
+            if is_private(column) {
                 continue;
             }
             inner_table.add_column(i as u8, column.to_string(), |c| c);
         }
-        let sort_by_column = columns
-            .iter()
-            .enumerate()
-            .find_map(|(i, c)| if *c == sort_by { Some(i) } else { None })
-            .expect("sort_by column not found in columns");
-        inner_table.sort_by(sort_by_column as u8, Ordering::Greater);
+        let find_column = |name: &str| {
+            columns.iter().enumerate().find_map(|(i, c)| {
+                if *c == name && !is_private(c) {
+                    Some(i)
+                } else {
+                    None
+                }
+            })
+        };
+        let sort_by_column = find_column(sort_by)
+            .ok_or_else(|| anyhow!("No column {} to sort by in {}", sort_by, view_name))?;
+        let mut sort_order = (sort_by_column, Ordering::Greater);
+        // --sort overrides the default (if the view has such column)
+        if let Some(sort) = context.lock().unwrap().options.view.sort.as_ref() {
+            if let Some(column) = find_column(&sort.column) {
+                sort_order = (
+                    column,
+                    if sort.ascending {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    },
+                );
+            } else {
+                log::warn!("No column {} for --sort in {}", sort.column, view_name);
+            }
+        }
+        inner_table.sort_by(sort_order.0 as u8, sort_order.1);
         inner_table.set_on_submit(|siv, _row, index| {
             if index.is_none() {
                 return;
@@ -216,7 +424,12 @@ impl QueryResultView {
         bg_runner.start(update_callback);
 
         let view = QueryResultView {
+            context,
+            view_name,
             table,
+            items: Vec::new(),
+            filter: String::new(),
+            query_filter,
             columns,
             columns_to_compare,
             on_submit: None,
@@ -230,12 +443,82 @@ impl ViewWrapper for QueryResultView {
     wrap_impl_no_move!(self.table: ExtTableView<Row, u8>);
 }
 
-fn parse_columns(columns: &[&'static str]) -> Vec<&'static str> {
+fn get_field(block: &Columns, i: usize, column: &str, sql_type: SqlType) -> Result<Field> {
+    let field = match sql_type {
+        SqlType::String => Field::String(block.get::<_, _>(i, column)?),
+        SqlType::Float64 => Field::Float64(block.get::<_, _>(i, column)?),
+        SqlType::Float32 => Field::Float32(block.get::<_, _>(i, column)?),
+        SqlType::UInt64 => Field::UInt64(block.get::<_, _>(i, column)?),
+        SqlType::UInt32 => Field::UInt32(block.get::<_, _>(i, column)?),
+        SqlType::UInt8 => Field::UInt8(block.get::<_, _>(i, column)?),
+        SqlType::Int64 => Field::Int64(block.get::<_, _>(i, column)?),
+        SqlType::Int32 => Field::Int32(block.get::<_, _>(i, column)?),
+        SqlType::Int8 => Field::Int8(block.get::<_, _>(i, column)?),
+        SqlType::DateTime(_) => Field::DateTime(
+            block
+                .get::<DateTime<Tz>, _>(i, column)?
+                .with_timezone(&Local),
+        ),
+        SqlType::Nullable(SqlType::String) => block
+            .get::<Option<String>, _>(i, column)?
+            .map_or(Field::Null, Field::String),
+        SqlType::Nullable(SqlType::Float64) => block
+            .get::<Option<f64>, _>(i, column)?
+            .map_or(Field::Null, Field::Float64),
+        SqlType::Nullable(SqlType::UInt64) => block
+            .get::<Option<u64>, _>(i, column)?
+            .map_or(Field::Null, Field::UInt64),
+        SqlType::Nullable(SqlType::Int64) => block
+            .get::<Option<i64>, _>(i, column)?
+            .map_or(Field::Null, Field::Int64),
+        SqlType::Nullable(SqlType::DateTime(_)) => block
+            .get::<Option<DateTime<Tz>>, _>(i, column)?
+            .map_or(Field::Null, |v| Field::DateTime(v.with_timezone(&Local))),
+        SqlType::Array(SqlType::String) => Field::Array(
+            block
+                .get::<Vec<String>, _>(i, column)?
+                .into_iter()
+                .map(Field::String)
+                .collect(),
+        ),
+        SqlType::Array(SqlType::Float64) => Field::Array(
+            block
+                .get::<Vec<f64>, _>(i, column)?
+                .into_iter()
+                .map(Field::Float64)
+                .collect(),
+        ),
+        SqlType::Array(SqlType::UInt64) => Field::Array(
+            block
+                .get::<Vec<u64>, _>(i, column)?
+                .into_iter()
+                .map(Field::UInt64)
+                .collect(),
+        ),
+        SqlType::Array(SqlType::Int64) => Field::Array(
+            block
+                .get::<Vec<i64>, _>(i, column)?
+                .into_iter()
+                .map(Field::Int64)
+                .collect(),
+        ),
+        _ => {
+            return Err(anyhow!(
+                "Type {:?} for column {} not implemented",
+                sql_type,
+                column
+            ))
+        }
+    };
+    return Ok(field);
+}
+
+fn parse_columns(columns: &[&str]) -> Vec<String> {
     let mut result = Vec::new();
     for column in columns.iter() {
         // NOTE: this is broken for "x AS `foo bar`"
         let column_name = column.split(' ').last().unwrap();
-        result.push(column_name);
+        result.push(column_name.to_string());
     }
     return result;
 }
@@ -8,6 +8,7 @@ use cursive::{
 };
 use humantime::format_duration;
 use size::{Base, SizeFormatter, Style};
+use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
 use std::time::Duration;
 
@@ -16,31 +17,62 @@ use crate::interpreter::{
 };
 
 pub struct SummaryView {
-    prev_summary: Option<ClickHouseServerSummary>,
+    // Last summaries (the last one is the previous summary), up to --summary-history
+    summaries: VecDeque<ClickHouseServerSummary>,
+    history: usize,
     prev_update_time: Option<DateTime<Local>>,
 
     layout: views::LinearLayout,
 
+    // --summary-thresholds
+    thresholds: HashMap<String, (f64, f64)>,
+
     #[allow(unused)]
     bg_runner: BackgroundRunner,
 }
 
-fn get_color_for_ratio(used: u64, total: u64) -> cursive::theme::Color {
+const DEFAULT_THRESHOLDS: (f64, f64) = (0.5, 0.9);
+
+// thresholds - (warning, critical) ratios
+fn get_color_for_ratio(used: u64, total: u64, thresholds: (f64, f64)) -> cursive::theme::Color {
     let q = used as f64 / total as f64;
-    return if q > 0.90 {
+    return if q > thresholds.1 {
         BaseColor::Red.dark()
-    } else if q > 0.5 {
+    } else if q > thresholds.0 {
         BaseColor::Yellow.dark()
     } else {
         BaseColor::Green.dark()
     };
 }
 
+fn sparkline(values: &[u64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or_default();
+    return values
+        .iter()
+        .map(|&v| {
+            if max == 0 {
+                return BARS[0];
+            }
+            return BARS[(v as f64 / max as f64 * (BARS.len() - 1) as f64).round() as usize];
+        })
+        .collect::<String>();
+}
+
 // TODO add new information:
 // - page cache usage (should be diffed)
 impl SummaryView {
     pub fn new(context: ContextArc) -> Self {
         let delay = context.lock().unwrap().options.view.delay_interval;
+        let thresholds = context
+            .lock()
+            .unwrap()
+            .options
+            .view
+            .summary_thresholds
+            .clone()
+            .unwrap_or_default();
+        let history = context.lock().unwrap().options.view.summary_history;
 
         let update_callback_context = context.clone();
         let update_callback = move || {
@@ -193,20 +225,33 @@ impl SummaryView {
                     )))
                     .child(views::DummyView.fixed_width(1))
                     .child(views::TextView::new("").with_name("mem")),
-            );
+            )
+            // Per disk read/write (only if there is more then one disk)
+            .child(views::TextView::new("").with_name("disks"));
 
         let bg_runner_cv = context.lock().unwrap().background_runner_cv.clone();
         let mut bg_runner = BackgroundRunner::new(delay, bg_runner_cv);
         bg_runner.start(update_callback);
 
         return Self {
-            prev_summary: None,
+            summaries: VecDeque::with_capacity(history.max(1)),
+            history,
             prev_update_time: None,
             layout,
+            thresholds,
             bg_runner,
         };
     }
 
+    fn get_color(&self, metric: &str, used: u64, total: u64) -> cursive::theme::Color {
+        let thresholds = self
+            .thresholds
+            .get(metric)
+            .copied()
+            .unwrap_or(DEFAULT_THRESHOLDS);
+        return get_color_for_ratio(used, total, thresholds);
+    }
+
     pub fn set_view_content<S>(&mut self, view_name: &str, content: S)
     where
         S: Into<StyledString> + Clone,
@@ -216,6 +261,25 @@ impl SummaryView {
         });
     }
 
+    // Sparkline for the metric over the last summaries (including the current one)
+    fn get_sparkline<F>(&self, summary: &ClickHouseServerSummary, metric: F) -> String
+    where
+        F: Fn(&ClickHouseServerSummary) -> u64,
+    {
+        if self.history == 0 {
+            return String::new();
+        }
+        let skip = (self.summaries.len() + 1).saturating_sub(self.history);
+        let values = self
+            .summaries
+            .iter()
+            .chain(std::iter::once(summary))
+            .skip(skip)
+            .map(metric)
+            .collect::<Vec<u64>>();
+        return format!(" {}", sparkline(&values));
+    }
+
     pub fn update(&mut self, summary: ClickHouseServerSummary) {
         let fmt = Rc::new(
             SizeFormatter::new()
@@ -256,10 +320,11 @@ impl SummaryView {
             let mut content = StyledString::plain("");
             content.append_styled(
                 fmt_ref.format(summary.memory.resident as i64),
-                get_color_for_ratio(summary.memory.resident, summary.memory.os_total),
+                self.get_color("memory", summary.memory.resident, summary.memory.os_total),
             );
             content.append_plain(" / ");
             content.append_plain(fmt_ref.format(summary.memory.os_total as i64));
+            content.append_plain(self.get_sparkline(&summary, |s| s.memory.resident));
             content.append_plain(format!(" ({})", description.join(", ")));
 
             self.set_view_content("mem", content);
@@ -270,10 +335,11 @@ impl SummaryView {
             let used_cpus = summary.cpu.user + summary.cpu.system;
             content.append_styled(
                 used_cpus.to_string(),
-                get_color_for_ratio(used_cpus, summary.cpu.count),
+                self.get_color("cpu", used_cpus, summary.cpu.count),
             );
             content.append_plain(" / ");
             content.append_plain(summary.cpu.count.to_string());
+            content.append_plain(self.get_sparkline(&summary, |s| s.cpu.user + s.cpu.system));
 
             self.set_view_content("cpu", content);
         }
@@ -301,24 +367,14 @@ impl SummaryView {
         }
 
         {
-            let mut pools: Vec<String> = Vec::new();
-            let mut add_pool = |prefix: &str, value: u64| {
-                if value > 0 {
-                    pools.push(format!("{}: {}", prefix, value));
-                }
-            };
-            add_pool("Merges", summary.threads.pools.merges_mutations);
-            add_pool("Fetches", summary.threads.pools.fetches);
-            add_pool("Common", summary.threads.pools.common);
-            add_pool("Moves", summary.threads.pools.moves);
-            add_pool("Schedule", summary.threads.pools.schedule);
-            add_pool("Buffer", summary.threads.pools.buffer_flush);
-            add_pool("Distributed", summary.threads.pools.distributed);
-            add_pool("Brokers", summary.threads.pools.message_broker);
-            add_pool("Backups", summary.threads.pools.backups);
-            add_pool("IO", summary.threads.pools.io);
-            add_pool("RemoteIO", summary.threads.pools.remote_io);
-            add_pool("Queries", summary.threads.pools.queries);
+            let pools = summary
+                .threads
+                .pools
+                .named()
+                .into_iter()
+                .filter(|(_, value)| *value > 0)
+                .map(|(prefix, value)| format!("{}: {}", prefix, value))
+                .collect::<Vec<String>>();
 
             self.set_view_content("pools", pools.join(", "));
         }
@@ -341,9 +397,33 @@ impl SummaryView {
             fmt_ref.format((summary.blkdev.write_bytes / update_interval) as i64),
         );
 
+        if summary.blkdev.devices.len() > 1 {
+            let mut content = StyledString::styled("Disks (read/write):", BaseColor::Cyan.dark());
+            content.append_plain(" ");
+            content.append_plain(
+                summary
+                    .blkdev
+                    .devices
+                    .iter()
+                    .map(|(device, read_bytes, write_bytes)| {
+                        format!(
+                            "{}: {}/{}",
+                            device,
+                            fmt_ref.format((read_bytes / update_interval) as i64),
+                            fmt_ref.format((write_bytes / update_interval) as i64)
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            );
+            self.set_view_content("disks", content);
+        } else {
+            self.set_view_content("disks", "");
+        }
+
         let mut selected_rows = summary.rows.selected / summary.uptime.server;
         let mut inserted_rows = summary.rows.inserted / summary.uptime.server;
-        if let Some(prev_summary) = &self.prev_summary {
+        if let Some(prev_summary) = self.summaries.back() {
             selected_rows =
                 (summary.rows.selected - prev_summary.rows.selected) * 1_000_000 / since_prev_us;
             inserted_rows =
@@ -363,8 +443,9 @@ impl SummaryView {
             let mut content = StyledString::plain("");
             content.append_styled(
                 summary.processes.to_string(),
-                get_color_for_ratio(summary.processes, summary.servers * 100),
+                self.get_color("queries", summary.processes, summary.servers * 100),
             );
+            content.append_plain(self.get_sparkline(&summary, |s| s.processes));
             self.set_view_content("queries", content);
         }
 
@@ -372,7 +453,7 @@ impl SummaryView {
             let mut content = StyledString::plain("");
             content.append_styled(
                 summary.merges.to_string(),
-                get_color_for_ratio(summary.merges, summary.servers * 20),
+                self.get_color("merges", summary.merges, summary.servers * 20),
             );
             self.set_view_content("merges", content);
         }
@@ -381,7 +462,7 @@ impl SummaryView {
             let mut content = StyledString::plain("");
             content.append_styled(
                 summary.mutations.to_string(),
-                get_color_for_ratio(summary.mutations, summary.servers * 8),
+                self.get_color("mutations", summary.mutations, summary.servers * 8),
             );
             self.set_view_content("mutations", content);
         }
@@ -390,12 +471,17 @@ impl SummaryView {
             let mut content = StyledString::plain("");
             content.append_styled(
                 summary.replication_queue.to_string(),
-                get_color_for_ratio(summary.replication_queue, summary.servers * 20),
+                self.get_color(
+                    "replication_queue",
+                    summary.replication_queue,
+                    summary.servers * 20,
+                ),
             );
             content.append(" (");
             content.append_styled(
                 summary.replication_queue_tries.to_string(),
-                get_color_for_ratio(
+                self.get_color(
+                    "replication_queue_tries",
                     summary.replication_queue_tries,
                     summary.replication_queue * 2,
                 ),
@@ -408,7 +494,7 @@ impl SummaryView {
             let mut content = StyledString::plain("");
             content.append_styled(
                 summary.fetches.to_string(),
-                get_color_for_ratio(summary.fetches, summary.servers * 20),
+                self.get_color("fetches", summary.fetches, summary.servers * 20),
             );
             self.set_view_content("fetches", content);
         }
@@ -417,7 +503,11 @@ impl SummaryView {
             let mut content = StyledString::plain("");
             content.append_styled(
                 fmt_ref.format(summary.storages.buffer_bytes as i64),
-                get_color_for_ratio(summary.storages.buffer_bytes, summary.memory.os_total),
+                self.get_color(
+                    "buffers",
+                    summary.storages.buffer_bytes,
+                    summary.memory.os_total,
+                ),
             );
             self.set_view_content("storage_buffer_bytes", content);
         }
@@ -426,14 +516,34 @@ impl SummaryView {
             let mut content = StyledString::plain("");
             content.append_styled(
                 summary.storages.distributed_insert_files.to_string(),
-                get_color_for_ratio(summary.storages.distributed_insert_files, 10000),
+                self.get_color(
+                    "distributed_inserts",
+                    summary.storages.distributed_insert_files,
+                    10000,
+                ),
             );
             self.set_view_content("storage_distributed_insert_files", content);
         }
 
-        self.prev_summary = Some(summary);
+        if self.summaries.len() >= self.history.max(1) {
+            self.summaries.pop_front();
+        }
+        self.summaries.push_back(summary);
         self.prev_update_time = Some(now);
     }
+
+    /// Tracked and OS total memory from the last summary (None if not yet loaded)
+    pub fn get_memory(&self) -> Option<(u64, u64)> {
+        return self
+            .summaries
+            .back()
+            .map(|summary| (summary.memory.tracked, summary.memory.os_total));
+    }
+
+    /// Comparable metrics from the last summary (None if not yet loaded)
+    pub fn get_metrics(&self) -> Option<Vec<(&'static str, u64)>> {
+        return self.summaries.back().map(|summary| summary.metrics());
+    }
 }
 
 impl View for SummaryView {
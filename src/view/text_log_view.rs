@@ -3,9 +3,17 @@ use std::sync::{Arc, Mutex};
 
 use chrono::{DateTime, Duration, Local};
 use chrono_tz::Tz;
-use cursive::view::ViewWrapper;
+use cursive::{
+    event::{Callback, Event, EventResult, Key},
+    view::{Resizable, ViewWrapper},
+    views::{EditView, LinearLayout, OnEventView, TextContent, TextView},
+    Cursive,
+};
 
-use crate::interpreter::{clickhouse::Columns, BackgroundRunner, ContextArc, WorkerEvent};
+use crate::interpreter::{
+    clickhouse::{Columns, TextLogFilter},
+    BackgroundRunner, ContextArc, WorkerEvent,
+};
 use crate::view::{LogEntry, LogView};
 use crate::wrap_impl_no_move;
 
@@ -13,9 +21,25 @@ pub type DateTime64 = DateTime<Local>;
 pub type DateTimeArc = Arc<Mutex<DateTime64>>;
 
 pub struct TextLogView {
-    inner_view: LogView,
+    // Active filters line + LogView
+    inner_view: LinearLayout,
+    filter_status: TextContent,
+
+    view_name: &'static str,
+    context: ContextArc,
+    query_ids: Option<Vec<String>>,
+    start: DateTime64,
+    end: Option<DateTime64>,
+    filter: Arc<Mutex<TextLogFilter>>,
     last_event_time_microseconds: DateTimeArc,
 
+    // Follow mode (like less +F), only for logs without query_ids (server logs):
+    // - follow - enabled by the user
+    // - following - follow is enabled and not paused by scrolling up (shared with the bg_runner)
+    can_follow: bool,
+    follow: bool,
+    following: Arc<Mutex<bool>>,
+
     #[allow(unused)]
     bg_runner: Option<BackgroundRunner>,
 }
@@ -23,6 +47,15 @@ pub struct TextLogView {
 // flush_interval_milliseconds for each *_log table from the config.xml/yml
 const FLUSH_INTERVAL_MILLISECONDS: i64 = 7500;
 
+// Levels for the filter (cycled with L), None - all levels
+const LOG_LEVELS: [Option<&str>; 5] = [
+    None,
+    Some("Debug"),
+    Some("Information"),
+    Some("Warning"),
+    Some("Error"),
+];
+
 impl TextLogView {
     pub fn new(
         view_name: &'static str,
@@ -35,10 +68,14 @@ impl TextLogView {
             Duration::try_milliseconds(FLUSH_INTERVAL_MILLISECONDS).unwrap();
         let query_start_microseconds = min_query_start_microseconds;
         let last_event_time_microseconds = Arc::new(Mutex::new(query_start_microseconds));
+        let following = Arc::new(Mutex::new(false));
+        let filter = Arc::new(Mutex::new(TextLogFilter::default()));
+        let mut end = max_query_end_microseconds;
 
         let delay = context.lock().unwrap().options.view.delay_interval;
 
         let mut bg_runner = None;
+        let mut can_follow = false;
         // Start pulling only if the query did not finished, i.e. we don't know the end time.
         // (but respect the FLUSH_INTERVAL_MILLISECONDS)
         let now = Local::now();
@@ -53,6 +90,7 @@ impl TextLogView {
             if query_ids.is_some() {
                 max_query_end_microseconds += Duration::try_seconds(3).unwrap();
             }
+            end = Some(max_query_end_microseconds);
             context
                 .lock()
                 .unwrap()
@@ -62,10 +100,40 @@ impl TextLogView {
                     query_ids.clone(),
                     query_start_microseconds,
                     Some(max_query_end_microseconds),
+                    TextLogFilter::default(),
                 ));
+
+            // Server logs, pull new entries only in follow mode
+            if query_ids.is_none() {
+                let update_last_event_time_microseconds = last_event_time_microseconds.clone();
+                let update_following = following.clone();
+                let update_filter = filter.clone();
+                let update_callback_context = context.clone();
+                let update_callback = move || {
+                    if !*update_following.lock().unwrap() {
+                        return;
+                    }
+                    update_callback_context.lock().unwrap().worker.send(
+                        WorkerEvent::GetQueryTextLog(
+                            view_name,
+                            None,
+                            *update_last_event_time_microseconds.lock().unwrap(),
+                            None,
+                            update_filter.lock().unwrap().clone(),
+                        ),
+                    );
+                };
+
+                let bg_runner_cv = context.lock().unwrap().background_runner_cv.clone();
+                let mut created_bg_runner = BackgroundRunner::new(delay, bg_runner_cv);
+                created_bg_runner.start(update_callback);
+                bg_runner = Some(created_bg_runner);
+                can_follow = true;
+            }
         } else {
             let update_query_ids = query_ids.clone();
             let update_last_event_time_microseconds = last_event_time_microseconds.clone();
+            let update_filter = filter.clone();
             let update_callback_context = context.clone();
             let update_callback =
                 move || {
@@ -75,6 +143,7 @@ impl TextLogView {
                             update_query_ids.clone(),
                             *update_last_event_time_microseconds.lock().unwrap(),
                             max_query_end_microseconds,
+                            update_filter.lock().unwrap().clone(),
                         ),
                     );
                 };
@@ -87,9 +156,22 @@ impl TextLogView {
 
         let is_cluster = context.lock().unwrap().options.clickhouse.cluster.is_some();
         let wrap = context.lock().unwrap().options.view.wrap;
+        let filter_status = TextContent::new("");
         let view = TextLogView {
-            inner_view: LogView::new(is_cluster, wrap),
+            inner_view: LinearLayout::vertical()
+                .child(TextView::new_with_content(filter_status.clone()))
+                .child(LogView::new(is_cluster, wrap)),
+            filter_status,
+            view_name,
+            context,
+            query_ids,
+            start: query_start_microseconds,
+            end,
+            filter,
             last_event_time_microseconds,
+            can_follow,
+            follow: false,
+            following,
             bg_runner,
         };
         return view;
@@ -119,13 +201,139 @@ impl TextLogView {
 
             logs.push(log_entry);
         }
+        drop(last_event_time_microseconds);
 
-        self.inner_view.push_logs(&logs);
+        self.logs().push_logs(&logs);
+        self.context.lock().unwrap().set_last_update(self.view_name);
 
         return Ok(());
     }
+
+    fn logs(&mut self) -> &mut LogView {
+        return self
+            .inner_view
+            .get_child_mut(1)
+            .unwrap()
+            .downcast_mut::<LogView>()
+            .unwrap();
+    }
+
+    fn set_filter(&mut self, filter: TextLogFilter) {
+        let mut status = Vec::new();
+        if let Some(max_level) = filter.max_level {
+            status.push(format!("level <= {}", max_level));
+        }
+        if !filter.message.is_empty() {
+            status.push(format!("message contains '{}'", filter.message));
+        }
+        if status.is_empty() {
+            self.filter_status.set_content("");
+        } else {
+            self.filter_status
+                .set_content(format!("Filter: {}", status.join(", ")));
+        }
+        log::trace!("Set logs filter: {:?}", filter);
+        *self.filter.lock().unwrap() = filter;
+
+        // Re-query logs from the beginning
+        self.logs().clear();
+        *self.last_event_time_microseconds.lock().unwrap() = self.start;
+        match self.bg_runner.as_mut() {
+            // Logs for running queries are pulled by the bg_runner
+            Some(bg_runner) if !self.can_follow => bg_runner.schedule(),
+            _ => {
+                self.context
+                    .lock()
+                    .unwrap()
+                    .worker
+                    .send(WorkerEvent::GetQueryTextLog(
+                        self.view_name,
+                        self.query_ids.clone(),
+                        self.start,
+                        self.end,
+                        self.filter.lock().unwrap().clone(),
+                    ));
+            }
+        }
+    }
+
+    fn cycle_max_level(&mut self) {
+        let mut filter = self.filter.lock().unwrap().clone();
+        let current = LOG_LEVELS
+            .iter()
+            .position(|level| *level == filter.max_level)
+            .unwrap_or_default();
+        filter.max_level = LOG_LEVELS[(current + 1) % LOG_LEVELS.len()];
+        self.set_filter(filter);
+    }
+
+    fn set_message_filter(&mut self, message: &str) {
+        let mut filter = self.filter.lock().unwrap().clone();
+        filter.message = message.to_string();
+        self.set_filter(filter);
+    }
+
+    fn show_message_filter_prompt(&self) -> EventResult {
+        let view_name = self.view_name;
+        let message = self.filter.lock().unwrap().message.clone();
+        return EventResult::Consumed(Some(Callback::from_fn(move |siv: &mut Cursive| {
+            let apply = move |siv: &mut Cursive, text: &str| {
+                siv.call_on_name(view_name, |v: &mut TextLogView| {
+                    v.set_message_filter(text);
+                });
+                siv.pop_layer();
+            };
+            let view = OnEventView::new(
+                EditView::new()
+                    .content(message.clone())
+                    .on_submit(apply)
+                    .min_width(10),
+            );
+            siv.add_layer(view);
+        })));
+    }
+
+    fn set_following(&mut self, following: bool) {
+        log::trace!("Follow: {} (following: {})", self.follow, following);
+        *self.following.lock().unwrap() = following;
+        self.logs().set_stick_to_bottom(following);
+        if following {
+            if let Some(bg_runner) = self.bg_runner.as_mut() {
+                bg_runner.schedule();
+            }
+        }
+    }
 }
 
 impl ViewWrapper for TextLogView {
-    wrap_impl_no_move!(self.inner_view: LogView);
+    wrap_impl_no_move!(self.inner_view: LinearLayout);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char('L') => {
+                self.cycle_max_level();
+                return EventResult::consumed();
+            }
+            Event::Char('m') => return self.show_message_filter_prompt(),
+            _ => {}
+        }
+        if self.can_follow && self.follow {
+            match event {
+                Event::Char('G') | Event::Key(Key::End) => self.set_following(true),
+                Event::Char('g')
+                | Event::Char('k')
+                | Event::Key(Key::Home)
+                | Event::Key(Key::Up)
+                | Event::Key(Key::PageUp)
+                | Event::CtrlChar('u') => self.set_following(false),
+                _ => {}
+            }
+        }
+        if self.can_follow && event == Event::Char('f') {
+            self.follow = !self.follow;
+            self.set_following(self.follow);
+            return EventResult::consumed();
+        }
+        return self.inner_view.on_event(event);
+    }
 }